@@ -1,99 +1,459 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 extern crate alloc;
 
 use alloc::string::{String, ToString as _};
 use alloc::vec::Vec;
+use alloy_primitives::Address;
 use alloy_rpc_types_eth::EIP1186AccountProofResponse;
+use anyhow::{anyhow, ensure};
 
-use storage_proof_core::consts::CW20_ADDR;
-use storage_proof_core::proof::verify_proof;
+use storage_proof_core::consts::{
+    coprocessor_domain, is_allowlisted_erc20, BALANCE_SLOT, CW20_ADDR,
+};
+use storage_proof_core::mint::cw20_mint_subroutine;
+use storage_proof_core::proof::{mapping_slot_key, verify_proof};
 use valence_coprocessor::Witness;
 
 use cosmwasm_std::{to_json_binary, Uint128};
 use valence_authorization_utils::{
-    authorization::{AtomicSubroutine, AuthorizationMsg, Priority, Subroutine},
-    authorization_message::{Message, MessageDetails, MessageType},
+    authorization::{AuthorizationMsg, Priority},
     domain::Domain,
-    function::AtomicFunction,
     msg::ProcessorMessage,
     zk_authorization::ZkMessage,
 };
 
+const NEUTRON_HRP: &str = "neutron";
+
 pub fn circuit(witnesses: Vec<Witness>) -> Vec<u8> {
-    assert!(
-        witnesses.len() == 2,
-        "Expected 2 witnesses: account state proof and neutron addr"
+    let statement = verify_witnesses(&witnesses).expect("circuit verification failed");
+
+    let zk_msg = build_zk_msg(&statement);
+
+    // the authorization contract parses the output as the zk message json
+    serde_json::to_vec(&zk_msg)
+        .expect("failed to serialize the zk authorization message to json vec")
+}
+
+/// statement proven by the circuit: `amount` cw20 tokens are to be
+/// minted to `recipient`, for the balance held at `block_number`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MintStatement {
+    pub recipient: String,
+    pub amount: u128,
+    pub block_number: u64,
+}
+
+/// pure circuit logic. verifies the account state proof witness and
+/// returns the mint statement, without building the zk message.
+pub fn verify_witnesses(witnesses: &[Witness]) -> anyhow::Result<MintStatement> {
+    ensure!(
+        witnesses.len() == 3,
+        "Expected 3 witnesses: account state proof, neutron addr and eth addr"
     );
 
     // extract the witnesses
     let state_proof_bytes = witnesses[0]
         .as_state_proof()
-        .expect("Failed to get state proof bytes");
+        .ok_or_else(|| anyhow!("Failed to get state proof bytes"))?;
     let neutron_addr_bytes = witnesses[1]
         .as_data()
-        .expect("failed to get neutron addr bytes");
+        .ok_or_else(|| anyhow!("failed to get neutron addr bytes"))?;
+    let eth_addr_bytes = witnesses[2]
+        .as_data()
+        .ok_or_else(|| anyhow!("failed to get eth addr bytes"))?;
+    ensure!(
+        eth_addr_bytes.len() == 20,
+        "invalid eth addr length: {}",
+        eth_addr_bytes.len()
+    );
+    let eth_addr = Address::from_slice(eth_addr_bytes);
+
+    ensure!(
+        state_proof_bytes.domain == coprocessor_domain(),
+        "state proof is from domain {}, expected {}",
+        state_proof_bytes.domain,
+        coprocessor_domain()
+    );
+
+    // the block the coprocessor validated the root for, as reported by the
+    // controller
+    let block_number: [u8; 8] = state_proof_bytes
+        .payload
+        .as_slice()
+        .try_into()
+        .map_err(|_| anyhow!("state proof payload is not a block number"))?;
 
     let proof: EIP1186AccountProofResponse = serde_json::from_slice(&state_proof_bytes.proof)
-        .expect("failed to deserialize the proof bytes");
+        .map_err(|e| anyhow!("failed to deserialize the proof bytes: {e}"))?;
 
-    verify_proof(&proof).expect("proof verification failed");
+    // the account proof binds the erc20 address to the domain state root
+    verify_proof(&proof, state_proof_bytes.root.into())
+        .map_err(|e| anyhow!("proof verification failed: {e}"))?;
+
+    ensure!(
+        is_allowlisted_erc20(&proof.address.into_array()),
+        "erc20 {} is not allowlisted",
+        proof.address
+    );
+
+    // the proven slot must be the balance of the holder, not any other
+    // slot of the token
+    ensure!(
+        proof.storage_proof[0].key.as_b256() == mapping_slot_key(eth_addr, BALANCE_SLOT),
+        "storage proof is not of the balance of {eth_addr} at slot {BALANCE_SLOT}"
+    );
 
     let neutron_addr = core::str::from_utf8(neutron_addr_bytes)
-        .expect("failed to convert neutron addr bytes to str");
+        .map_err(|e| anyhow!("failed to convert neutron addr bytes to str: {e}"))?;
+    validate_neutron_addr(neutron_addr)?;
 
     let evm_balance = proof.storage_proof[0].value;
     let evm_balance_u128: u128 = evm_balance
         .try_into()
-        .expect("failed to parse U256 -> u128");
+        .map_err(|_| anyhow!("failed to parse U256 -> u128"))?;
 
-    let zk_msg = build_zk_msg(neutron_addr.to_string(), evm_balance_u128);
+    // cw20 rejects zero mints, which would fail the message after the
+    // proof was already accepted
+    ensure!(evm_balance_u128 != 0, "holder has no balance to mint");
 
-    serde_json::to_vec(&zk_msg)
-        .expect("failed to serialize the zk authorization message to json vec")
+    Ok(MintStatement {
+        recipient: neutron_addr.to_string(),
+        amount: evm_balance_u128,
+        block_number: u64::from_be_bytes(block_number),
+    })
 }
 
-pub fn build_zk_msg(recipient: String, amount: u128) -> ZkMessage {
+/// ensures the mint recipient is a bech32 neutron account or contract address
+fn validate_neutron_addr(addr: &str) -> anyhow::Result<()> {
+    let (hrp, data) =
+        bech32::decode(addr).map_err(|e| anyhow!("invalid neutron addr {addr}: {e}"))?;
+
+    ensure!(
+        hrp.as_str() == NEUTRON_HRP,
+        "invalid neutron addr prefix: {}",
+        hrp.as_str()
+    );
+    ensure!(
+        data.len() == 20 || data.len() == 32,
+        "invalid neutron addr length: {}",
+        data.len()
+    );
+
+    Ok(())
+}
+
+pub fn build_zk_msg(statement: &MintStatement) -> ZkMessage {
     let mint_cw20_msg = cw20::Cw20ExecuteMsg::Mint {
-        recipient,
-        amount: Uint128::new(amount),
+        recipient: statement.recipient.clone(),
+        amount: Uint128::new(statement.amount),
     };
 
     let processor_msg = ProcessorMessage::CosmwasmExecuteMsg {
         msg: to_json_binary(&mint_cw20_msg).unwrap(),
     };
 
-    let function = AtomicFunction {
-        domain: Domain::Main,
-        message_details: MessageDetails {
-            message_type: MessageType::CosmwasmExecuteMsg,
-            message: Message {
-                name: "mint".to_string(),
-                params_restrictions: None,
-            },
-        },
-        contract_address: valence_library_utils::LibraryAccountType::Addr(CW20_ADDR.to_string()),
-    };
-
-    let subroutine = AtomicSubroutine {
-        functions: Vec::from([function]),
-        retry_logic: None,
-        expiration_time: None,
-    };
-
     let message = AuthorizationMsg::EnqueueMsgs {
         id: 0,
         msgs: Vec::from([processor_msg]),
-        subroutine: Subroutine::Atomic(subroutine),
+        subroutine: cw20_mint_subroutine(CW20_ADDR),
         priority: Priority::Medium,
         expiration_time: None,
     };
 
     ZkMessage {
         registry: 0,
-        block_number: 0,
+        block_number: statement.block_number,
         domain: Domain::Main,
         authorization_contract: None,
         message,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::str::FromStr;
+    use std::{env, fs, path::PathBuf};
+    use storage_proof_core::{fixture::Fixture, output::split_public_inputs};
+    use valence_authorization_utils::authorization::Subroutine;
+    use valence_coprocessor::StateProof;
+
+    const USDC_FIXTURE: &[u8] = include_bytes!("../../core/fixtures/usdc.json");
+    const ZERO_BALANCE_FIXTURE: &[u8] = include_bytes!("../../core/fixtures/zero_balance.json");
+    const PROXY_FIXTURE: &[u8] = include_bytes!("../../core/fixtures/proxy.json");
+    const MULTI_SLOT_FIXTURE: &[u8] = include_bytes!("../../core/fixtures/multi_slot.json");
+    const NOT_ALLOWLISTED_FIXTURE: &[u8] =
+        include_bytes!("../../core/fixtures/not_allowlisted.json");
+    const WRONG_SLOT_FIXTURE: &[u8] = include_bytes!("../../core/fixtures/wrong_slot.json");
+
+    fn usdc_fixture() -> Fixture {
+        Fixture::from_slice(USDC_FIXTURE).unwrap()
+    }
+
+    /// builds the witnesses the controller computes for a fixture
+    fn witnesses(fixture: &Fixture) -> Vec<Witness> {
+        let state_proof = StateProof {
+            domain: fixture.domain.clone(),
+            root: fixture.block.root.0,
+            payload: fixture.block.number.to_be_bytes().to_vec(),
+            proof: serde_json::to_vec(&fixture.proof).unwrap(),
+        };
+
+        Vec::from([
+            Witness::StateProof(state_proof),
+            Witness::Data(fixture.inputs.neutron_addr.as_bytes().to_vec()),
+            Witness::Data(
+                Address::from_str(&fixture.inputs.eth_addr)
+                    .unwrap()
+                    .to_vec(),
+            ),
+        ])
+    }
+
+    /// compares the circuit output against `golden/<name>.json`.
+    /// run with `UPDATE_GOLDEN=1` to rewrite the golden file instead.
+    fn assert_golden(name: &str, output: &[u8]) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("golden")
+            .join(format!("{name}.json"));
+
+        if env::var("UPDATE_GOLDEN").is_ok() {
+            fs::write(&path, output).unwrap();
+            return;
+        }
+
+        let golden = fs::read(&path).unwrap();
+        assert!(
+            golden == output,
+            "circuit output differs from {path:?}:\n{}",
+            String::from_utf8_lossy(output)
+        );
+    }
+
+    #[test]
+    fn test_circuit_golden_usdc() {
+        let fixture = usdc_fixture();
+        let output = circuit(witnesses(&fixture));
+
+        assert_golden("usdc", &output);
+    }
+
+    #[test]
+    fn test_zk_msg_matches_processor_execution() {
+        let fixture = usdc_fixture();
+        let output = circuit(witnesses(&fixture));
+
+        // the public inputs of the proof are the coprocessor root followed
+        // by the output, decoded the way the authorization contract does
+        let mut inputs = [1u8; 32].to_vec();
+        inputs.extend_from_slice(&output);
+        let (_, zk_msg) = split_public_inputs(&inputs).unwrap();
+        let zk_msg: ZkMessage = cosmwasm_std::from_json(zk_msg).unwrap();
+        assert_eq!(zk_msg.block_number, fixture.block.number);
+        let AuthorizationMsg::EnqueueMsgs {
+            msgs, subroutine, ..
+        } = zk_msg.message
+        else {
+            panic!("circuit must emit an enqueue msgs message");
+        };
+        let Subroutine::Atomic(subroutine) = subroutine else {
+            panic!("circuit must emit an atomic subroutine");
+        };
+
+        // the processor executes each message on the contract of the
+        // function at the same position
+        assert_eq!(msgs.len(), 1);
+        assert_eq!(subroutine.functions.len(), msgs.len());
+        let function = &subroutine.functions[0];
+        assert_eq!(
+            serde_json::to_value(&function.contract_address).unwrap(),
+            serde_json::json!({ "|library_account_addr|": CW20_ADDR })
+        );
+
+        let ProcessorMessage::CosmwasmExecuteMsg { msg } = &msgs[0] else {
+            panic!("circuit must emit a cosmwasm execute msg");
+        };
+
+        // the executed message is the one the function allows
+        let msg_json: serde_json::Value = serde_json::from_slice(msg.as_slice()).unwrap();
+        assert!(msg_json
+            .get(&function.message_details.message.name)
+            .is_some());
+
+        // and mints the proven balance to the requested recipient
+        let statement = verify_witnesses(&witnesses(&fixture)).unwrap();
+        let mint: cw20::Cw20ExecuteMsg = cosmwasm_std::from_json(msg).unwrap();
+        assert_eq!(
+            mint,
+            cw20::Cw20ExecuteMsg::Mint {
+                recipient: fixture.inputs.neutron_addr,
+                amount: Uint128::new(statement.amount),
+            }
+        );
+    }
+
+    #[test]
+    fn test_verify_witnesses_usdc() {
+        let fixture = usdc_fixture();
+        let statement = verify_witnesses(&witnesses(&fixture)).unwrap();
+
+        assert_eq!(statement.recipient, fixture.inputs.neutron_addr);
+        assert_eq!(statement.amount, 7_191_271);
+        assert_eq!(statement.block_number, fixture.block.number);
+    }
+
+    #[test]
+    fn test_verify_witnesses_proxy() {
+        let fixture = Fixture::from_slice(PROXY_FIXTURE).unwrap();
+        let statement = verify_witnesses(&witnesses(&fixture)).unwrap();
+
+        assert_eq!(
+            statement.amount,
+            fixture.proof.storage_proof[0].value.to::<u128>()
+        );
+    }
+
+    #[test]
+    fn test_verify_witnesses_zero_balance() {
+        let fixture = Fixture::from_slice(ZERO_BALANCE_FIXTURE).unwrap();
+        let err = verify_witnesses(&witnesses(&fixture)).unwrap_err();
+
+        assert!(err.to_string().contains("no balance"), "{err}");
+    }
+
+    #[test]
+    fn test_verify_witnesses_multi_slot() {
+        let fixture = Fixture::from_slice(MULTI_SLOT_FIXTURE).unwrap();
+
+        assert!(verify_witnesses(&witnesses(&fixture)).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "circuit verification failed")]
+    fn test_circuit_tampered_proof_value() {
+        let mut fixture = usdc_fixture();
+        fixture.proof.storage_proof[0].value += alloy_primitives::U256::from(1);
+
+        circuit(witnesses(&fixture));
+    }
+
+    #[test]
+    #[should_panic(expected = "circuit verification failed")]
+    fn test_circuit_tampered_proof_nodes() {
+        let mut fixture = usdc_fixture();
+        fixture.proof.storage_proof[0].proof.rotate_left(1);
+
+        circuit(witnesses(&fixture));
+    }
+
+    #[test]
+    #[should_panic(expected = "circuit verification failed")]
+    fn test_circuit_wrong_witness_order() {
+        let mut witnesses = witnesses(&usdc_fixture());
+        witnesses.swap(0, 1);
+
+        circuit(witnesses);
+    }
+
+    #[test]
+    #[should_panic(expected = "circuit verification failed")]
+    fn test_circuit_missing_witness() {
+        let mut witnesses = witnesses(&usdc_fixture());
+        witnesses.pop();
+
+        circuit(witnesses);
+    }
+
+    #[test]
+    fn test_verify_witnesses_bad_recipient() {
+        let mut fixture = usdc_fixture();
+
+        for recipient in [
+            // not bech32
+            "not-an-address",
+            // wrong prefix
+            "cosmos1m6w8n0hluq7avn40hj0n6jnj8ejhykfr2k63gs",
+            // bad checksum
+            "neutron1m6w8n0hluq7avn40hj0n6jnj8ejhykfrwfnnjj",
+            "",
+        ] {
+            fixture.inputs.neutron_addr = recipient.to_string();
+
+            assert!(
+                verify_witnesses(&witnesses(&fixture)).is_err(),
+                "{recipient} must be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_witnesses_erc20_not_allowlisted() {
+        // a valid proof, of a token the circuit was not built for
+        let fixture = Fixture::from_slice(NOT_ALLOWLISTED_FIXTURE).unwrap();
+        let err = verify_witnesses(&witnesses(&fixture)).unwrap_err();
+
+        assert!(err.to_string().contains("not allowlisted"), "{err}");
+    }
+
+    #[test]
+    fn test_verify_witnesses_wrong_slot() {
+        // a valid proof of the holder balance, at another slot than the
+        // balances slot of the params
+        let fixture = Fixture::from_slice(WRONG_SLOT_FIXTURE).unwrap();
+        let err = verify_witnesses(&witnesses(&fixture)).unwrap_err();
+
+        assert!(err.to_string().contains("not of the balance"), "{err}");
+    }
+
+    #[test]
+    fn test_verify_witnesses_other_holder() {
+        // the proven balance is not the one of the requested holder
+        let mut fixture = usdc_fixture();
+        fixture.inputs.eth_addr = "0x000000000000000000000000000000000000dEaD".to_string();
+        let err = verify_witnesses(&witnesses(&fixture)).unwrap_err();
+
+        assert!(err.to_string().contains("not of the balance"), "{err}");
+
+        let mut witnesses = witnesses(&usdc_fixture());
+        witnesses[2] = Witness::Data(Vec::from([0u8; 19]));
+        assert!(verify_witnesses(&witnesses).is_err());
+    }
+
+    #[test]
+    fn test_verify_witnesses_account_not_in_state() {
+        // the storage proof is valid, but the account is not the one of
+        // the proven state
+        let mut fixture = usdc_fixture();
+        fixture.block.root = Fixture::from_slice(PROXY_FIXTURE).unwrap().block.root;
+
+        assert!(verify_witnesses(&witnesses(&fixture)).is_err());
+    }
+
+    #[test]
+    fn test_verify_witnesses_wrong_domain() {
+        let mut fixture = usdc_fixture();
+        fixture.domain = "ethereum-sepolia".to_string();
+
+        assert!(verify_witnesses(&witnesses(&fixture)).is_err());
+    }
+
+    #[test]
+    fn test_verify_witnesses_bad_payload() {
+        let mut witnesses = witnesses(&usdc_fixture());
+        let Witness::StateProof(state_proof) = &mut witnesses[0] else {
+            unreachable!()
+        };
+        state_proof.payload.pop();
+
+        assert!(verify_witnesses(&witnesses).is_err());
+    }
+
+    #[test]
+    fn test_verify_witnesses_non_utf8_recipient() {
+        let mut witnesses = witnesses(&usdc_fixture());
+        witnesses[1] = Witness::Data(Vec::from([0xff, 0xfe]));
+
+        assert!(verify_witnesses(&witnesses).is_err());
+    }
+}
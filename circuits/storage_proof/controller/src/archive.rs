@@ -0,0 +1,309 @@
+use alloc::{format, string::String, vec::Vec};
+use alloy_primitives::keccak256;
+use serde::{Deserialize, Serialize};
+
+use crate::{commands::read_storage_file, host::ControllerHost};
+
+/// virtual filesystem path of the proof archive index head, holding the
+/// number of entries. each `IndexEntry` is stored in its own file at
+/// `index_entry_path(position)`, so recording or archiving a request
+/// rewrites its entry alone.
+pub const ARCHIVE_INDEX_PATH: &str = "/var/share/proofs.idx";
+
+/// directory holding the archived proofs and the index entries
+const ARCHIVE_DIR: &str = "/var/share";
+
+/// extension of the archived proof files
+const ARCHIVE_EXT: &str = "prf";
+
+/// extension of the index entry files
+const INDEX_EXT: &str = "ide";
+
+/// metadata of a single proof request, recorded when its witnesses
+/// are computed and completed once the proof is delivered.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// erc20 balance holder on ethereum
+    pub holder: String,
+    /// erc20 contract the balance was proven against
+    pub erc20: String,
+    /// ethereum block number of the state proof
+    pub block: u64,
+    /// domain root the state proof was opened against
+    pub root: String,
+    /// proven erc20 balance, in base units
+    pub amount: String,
+    /// neutron address the cw20 mint is addressed to
+    pub recipient: String,
+    /// identifier the requester attached to the proof request
+    #[serde(default)]
+    pub request: Option<String>,
+    /// virtual filesystem path of the archived proof, assigned when the
+    /// entry is recorded
+    pub path: String,
+    /// whether the proof was delivered and written to `path`
+    pub archived: bool,
+}
+
+/// index of every proof request handled by the controller
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofIndex {
+    pub entries: Vec<IndexEntry>,
+}
+
+/// FAT-16 compatible 8.3 archive path of the file name `name`
+pub fn archive_path(name: u32) -> String {
+    format!("{ARCHIVE_DIR}/{name:08x}.{ARCHIVE_EXT}")
+}
+
+/// FAT-16 compatible 8.3 path of the index entry at `position`
+pub fn index_entry_path(position: usize) -> String {
+    format!("{ARCHIVE_DIR}/{position:08x}.{INDEX_EXT}")
+}
+
+/// preferred archive file name of a request: the first 4 bytes of
+/// `keccak256(holder || block_be || request)`. 8.3 names can't hold the
+/// full hash, so taken names are resolved when the entry is recorded.
+fn archive_name(entry: &IndexEntry) -> u32 {
+    let mut preimage = Vec::with_capacity(28);
+    preimage.extend(entry.holder.to_lowercase().as_bytes());
+    preimage.extend(entry.block.to_be_bytes());
+    preimage.extend(entry.request.as_deref().unwrap_or_default().as_bytes());
+
+    let digest = keccak256(preimage);
+
+    u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]])
+}
+
+impl ProofIndex {
+    /// records a pending proof request, replacing a previous request with
+    /// the same holder, block and request identifier in place. a new entry
+    /// is given its preferred archive path, or the next free one if it is
+    /// taken. returns the position of the recorded entry.
+    pub fn record(&mut self, mut entry: IndexEntry) -> anyhow::Result<usize> {
+        if let Some(i) = self.entries.iter().position(|e| same_key(e, &entry)) {
+            entry.path = self.entries[i].path.clone();
+            self.entries[i] = entry;
+
+            return Ok(i);
+        }
+
+        // every position has an 8 hex digit entry file name
+        anyhow::ensure!(
+            self.entries.len() <= u32::MAX as usize,
+            "proof index is full"
+        );
+
+        let name = archive_name(&entry);
+        entry.path = (0..=u32::MAX)
+            .map(|i| archive_path(name.wrapping_add(i)))
+            .find(|path| self.entries.iter().all(|e| &e.path != path))
+            .ok_or_else(|| anyhow::anyhow!("no free archive path left"))?;
+        self.entries.push(entry);
+
+        Ok(self.entries.len() - 1)
+    }
+
+    /// marks the pending request `request` of `holder` as archived,
+    /// returning its position so the delivered proof can be written to its
+    /// path.
+    pub fn archive(&mut self, holder: &str, request: &str) -> anyhow::Result<usize> {
+        let mut pending = self.entries.iter().enumerate().filter(|(_, e)| {
+            !e.archived && same_holder(&e.holder, holder) && e.request.as_deref() == Some(request)
+        });
+
+        let (i, _) = pending.next().ok_or_else(|| {
+            anyhow::anyhow!("no pending proof request {request} for holder {holder}")
+        })?;
+        anyhow::ensure!(
+            pending.next().is_none(),
+            "request identifier {request} of holder {holder} is ambiguous"
+        );
+
+        self.entries[i].archived = true;
+
+        Ok(i)
+    }
+
+    /// returns the entries matching every given filter
+    pub fn query(&self, holder: Option<&str>, block: Option<u64>) -> Vec<IndexEntry> {
+        self.entries
+            .iter()
+            .filter(|e| holder.is_none_or(|h| same_holder(&e.holder, h)))
+            .filter(|e| block.is_none_or(|b| e.block == b))
+            .cloned()
+            .collect()
+    }
+}
+
+/// loads the proof index from the virtual filesystem. a missing or
+/// cleared head is an empty index.
+pub fn load_index<H: ControllerHost>(host: &H) -> anyhow::Result<ProofIndex> {
+    let head = read_storage_file(host, ARCHIVE_INDEX_PATH)?;
+    if head.is_empty() {
+        return Ok(ProofIndex::default());
+    }
+
+    let len: usize = serde_json::from_slice(&head)
+        .map_err(|e| anyhow::anyhow!("corrupted proof index head: {e}"))?;
+    let entries = (0..len)
+        .map(|i| {
+            let bytes = read_storage_file(host, &index_entry_path(i))?;
+            serde_json::from_slice(&bytes)
+                .map_err(|e| anyhow::anyhow!("corrupted proof index entry {i}: {e}"))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(ProofIndex { entries })
+}
+
+/// persists the entry at `position` of the proof index. the entry is
+/// written before the head, so a failed write of a new entry leaves the
+/// index as it was.
+pub fn save_entry<H: ControllerHost>(
+    host: &H,
+    index: &ProofIndex,
+    position: usize,
+) -> anyhow::Result<()> {
+    let entry = index
+        .entries
+        .get(position)
+        .ok_or_else(|| anyhow::anyhow!("no proof index entry {position}"))?;
+    host.set_storage_file(&index_entry_path(position), &serde_json::to_vec(entry)?)?;
+
+    host.set_storage_file(
+        ARCHIVE_INDEX_PATH,
+        &serde_json::to_vec(&index.entries.len())?,
+    )
+}
+
+fn same_key(a: &IndexEntry, b: &IndexEntry) -> bool {
+    a.block == b.block && a.request == b.request && same_holder(&a.holder, &b.holder)
+}
+
+// ethereum addresses may arrive checksummed or lowercase
+fn same_holder(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::MemoryHost;
+
+    const HOLDER: &str = "0x8d41bb082C6050893d1eC113A104cc4C087F2a2a";
+
+    fn entry(holder: &str, block: u64, request: &str) -> IndexEntry {
+        IndexEntry {
+            holder: holder.to_string(),
+            erc20: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(),
+            block,
+            root: "0x00".to_string(),
+            amount: "7191271".to_string(),
+            recipient: "neutron1m6w8n0hluq7avn40hj0n6jnj8ejhykfrwfnnjh".to_string(),
+            request: Some(request.to_string()),
+            path: String::new(),
+            archived: false,
+        }
+    }
+
+    #[test]
+    fn test_archive_path_is_8_3() {
+        let mut index = ProofIndex::default();
+        index.record(entry(HOLDER, 22_000_000, "a")).unwrap();
+        index.record(entry(HOLDER, 22_000_001, "a")).unwrap();
+
+        let path = &index.entries[0].path;
+        let file = path.rsplit('/').next().unwrap();
+        let (name, ext) = file.split_once('.').unwrap();
+
+        assert_eq!(name.len(), 8);
+        assert_eq!(ext.len(), 3);
+        assert_ne!(path, &index.entries[1].path);
+    }
+
+    #[test]
+    fn test_record_replaces_same_key() {
+        let mut index = ProofIndex::default();
+        index.record(entry(HOLDER, 1, "a")).unwrap();
+        let path = index.entries[0].path.clone();
+        index.record(entry(HOLDER, 1, "b")).unwrap();
+        // replaced in place, keeping its position and path
+        assert_eq!(
+            index.record(entry(&HOLDER.to_lowercase(), 1, "a")).unwrap(),
+            0
+        );
+        index.record(entry(HOLDER, 2, "a")).unwrap();
+
+        assert_eq!(index.entries.len(), 3);
+        assert_eq!(index.entries[0].path, path);
+    }
+
+    #[test]
+    fn test_record_resolves_path_collision() {
+        let mut index = ProofIndex::default();
+        index.record(entry(HOLDER, 1, "a")).unwrap();
+
+        // a different request whose preferred name is already taken
+        let taken = entry(HOLDER, 2, "a");
+        index.entries[0].path = archive_path(archive_name(&taken));
+        index.record(taken).unwrap();
+
+        assert_eq!(index.entries.len(), 2);
+        assert_eq!(
+            index.entries[1].path,
+            archive_path(archive_name(&index.entries[1]).wrapping_add(1))
+        );
+    }
+
+    #[test]
+    fn test_archive_and_query() {
+        let mut index = ProofIndex::default();
+        index.record(entry(HOLDER, 5, "a")).unwrap();
+        index.record(entry(HOLDER, 1, "b")).unwrap();
+
+        // the delivered request is archived, not the most recent block
+        let archived = index.archive(&HOLDER.to_lowercase(), "b").unwrap();
+        assert_eq!(index.entries[archived].block, 1);
+        assert!(index.query(None, Some(1))[0].archived);
+        assert!(!index.query(None, Some(5))[0].archived);
+
+        assert_eq!(index.query(Some(HOLDER), None).len(), 2);
+        assert!(index.query(Some(HOLDER), Some(3)).is_empty());
+        assert!(index.archive(HOLDER, "b").is_err());
+        assert!(index
+            .archive("0x0000000000000000000000000000000000000000", "a")
+            .is_err());
+
+        index.record(entry(HOLDER, 6, "a")).unwrap();
+        assert!(index.archive(HOLDER, "a").is_err());
+    }
+
+    #[test]
+    fn test_index_writes_one_file_per_entry() {
+        let host = MemoryHost::new();
+        assert_eq!(load_index(&host).unwrap(), ProofIndex::default());
+
+        let mut index = ProofIndex::default();
+        for block in 0..3 {
+            let position = index.record(entry(HOLDER, block, "a")).unwrap();
+            save_entry(&host, &index, position).unwrap();
+        }
+        let first = host.file(&index_entry_path(0)).unwrap();
+
+        // ambiguous among the 3 pending requests
+        assert!(index.archive(HOLDER, "a").is_err());
+        index.entries[2].request = Some("b".to_string());
+        let position = index.archive(HOLDER, "b").unwrap();
+        save_entry(&host, &index, position).unwrap();
+
+        assert_eq!(load_index(&host).unwrap(), index);
+        // entries other than the archived one are left untouched
+        assert_eq!(host.file(&index_entry_path(0)).unwrap(), first);
+        assert_eq!(index_entry_path(10), "/var/share/0000000a.ide");
+
+        host.set_storage_file(&index_entry_path(1), b"not json")
+            .unwrap();
+        assert!(load_index(&host).is_err());
+    }
+}
@@ -0,0 +1,278 @@
+use alloc::{
+    format,
+    string::{String, ToString as _},
+    vec::Vec,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{archive, host::ControllerHost};
+
+/// virtual filesystem path of the proof history log head, holding the
+/// number of entries. each `HistoryEntry` is stored in its own file at
+/// `history_entry_path(seq)`, so appending never rewrites earlier entries.
+pub const HISTORY_PATH: &str = "/var/share/history.log";
+
+/// directory holding the proof history log entries
+const HISTORY_DIR: &str = "/var/share";
+
+/// extension of the proof history log entry files
+const HISTORY_EXT: &str = "hst";
+
+/// commands accepted by the controller entrypoint. the command is
+/// read from the `payload` object the proof request was submitted with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum EntrypointCmd {
+    /// write the delivered proof to `path`, overwriting any previous contents
+    Store { path: String },
+    /// append the delivered proof to the proof history log
+    Append,
+    /// clear the file at `path`
+    Delete { path: String },
+    /// list the proof history log entries without their proof bodies
+    List,
+    /// return the most recent proof history log entry
+    Latest,
+    /// write the delivered proof to the archive path of the pending proof
+    /// request `request` of `holder`, and mark it archived in the index.
+    /// failed deliveries are not archived.
+    Archive { holder: String, request: String },
+    /// look up indexed proof requests by holder and/or block number
+    Query {
+        #[serde(default)]
+        holder: Option<String>,
+        #[serde(default)]
+        block: Option<u64>,
+    },
+}
+
+/// single entry of the proof history log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// position of the entry in the log, starting at 0
+    pub seq: u64,
+    /// whether the coprocessor reported a successful proof computation
+    pub success: bool,
+    /// entrypoint arguments exactly as delivered by the coprocessor
+    pub args: Value,
+}
+
+impl EntrypointCmd {
+    /// parses the command from the entrypoint arguments `payload` field
+    pub fn from_args(args: &Value) -> anyhow::Result<Self> {
+        let payload = args
+            .get("payload")
+            .ok_or_else(|| anyhow::anyhow!("entrypoint arguments are missing the payload"))?;
+
+        serde_json::from_value(payload.clone())
+            .map_err(|e| anyhow::anyhow!("invalid entrypoint payload: {e}"))
+    }
+
+    /// executes the command against the controller virtual filesystem
+    pub fn execute<H: ControllerHost>(self, host: &H, args: &Value) -> anyhow::Result<Value> {
+        match self {
+            EntrypointCmd::Store { path } => {
+                let bytes = serde_json::to_vec(args)?;
+                host.set_storage_file(&path, &bytes)?;
+
+                Ok(args.clone())
+            }
+            EntrypointCmd::Append => {
+                let entry = HistoryEntry {
+                    seq: history_len(host)?,
+                    success: args["success"].as_bool().unwrap_or_default(),
+                    args: args.clone(),
+                };
+
+                // the entry is written before the head, so a failed append
+                // leaves the log as it was
+                let path = history_entry_path(entry.seq);
+                host.set_storage_file(&path, &serde_json::to_vec(&entry)?)?;
+                host.set_storage_file(HISTORY_PATH, &encode_history_len(entry.seq + 1)?)?;
+
+                Ok(json!({ "path": path, "seq": entry.seq }))
+            }
+            EntrypointCmd::Delete { path } => {
+                host.set_storage_file(&path, &[])?;
+
+                Ok(json!({ "deleted": path }))
+            }
+            EntrypointCmd::List => {
+                let entries = (0..history_len(host)?)
+                    .map(|seq| {
+                        let e = read_history_entry(host, seq)?;
+                        Ok(json!({ "seq": e.seq, "success": e.success, "payload": e.args["payload"] }))
+                    })
+                    .collect::<anyhow::Result<Vec<Value>>>()?;
+
+                Ok(json!({ "entries": entries }))
+            }
+            EntrypointCmd::Latest => {
+                let latest = match history_len(host)? {
+                    0 => None,
+                    len => Some(read_history_entry(host, len - 1)?),
+                };
+
+                Ok(serde_json::to_value(latest)?)
+            }
+            EntrypointCmd::Archive { holder, request } => {
+                anyhow::ensure!(
+                    args["success"].as_bool().unwrap_or_default(),
+                    "proof request {request} failed, nothing was archived"
+                );
+
+                let mut index = archive::load_index(host)?;
+                let position = index.archive(&holder, &request)?;
+                let entry = &index.entries[position];
+
+                host.set_storage_file(&entry.path, &serde_json::to_vec(args)?)?;
+                archive::save_entry(host, &index, position)?;
+
+                Ok(serde_json::to_value(entry)?)
+            }
+            EntrypointCmd::Query { holder, block } => {
+                let entries = archive::load_index(host)?.query(holder.as_deref(), block);
+
+                Ok(json!({ "entries": entries }))
+            }
+        }
+    }
+}
+
+/// reads a file from the virtual filesystem. files that were never
+/// written read as empty; any other read failure is propagated.
+pub(crate) fn read_storage_file<H: ControllerHost>(
+    host: &H,
+    path: &str,
+) -> anyhow::Result<Vec<u8>> {
+    Ok(host.get_storage_file(path)?.unwrap_or_default())
+}
+
+/// derives the FAT-16 compatible 8.3 path of the history entry `seq`
+pub fn history_entry_path(seq: u64) -> String {
+    format!("{HISTORY_DIR}/{seq:08x}.{HISTORY_EXT}")
+}
+
+/// number of entries in the proof history log. a missing or cleared
+/// head is an empty log.
+pub fn history_len<H: ControllerHost>(host: &H) -> anyhow::Result<u64> {
+    let head = read_storage_file(host, HISTORY_PATH)?;
+    if head.is_empty() {
+        return Ok(0);
+    }
+
+    serde_json::from_slice(&head).map_err(|e| anyhow::anyhow!("corrupted history log head: {e}"))
+}
+
+fn encode_history_len(len: u64) -> anyhow::Result<Vec<u8>> {
+    anyhow::ensure!(len <= u32::MAX as u64 + 1, "history log is full");
+
+    Ok(serde_json::to_vec(&len)?)
+}
+
+/// reads the history entry `seq` from its own file
+pub fn read_history_entry<H: ControllerHost>(host: &H, seq: u64) -> anyhow::Result<HistoryEntry> {
+    let bytes = read_storage_file(host, &history_entry_path(seq))?;
+
+    serde_json::from_slice(&bytes)
+        .map_err(|e| anyhow::anyhow!("corrupted history log entry {seq}: {e}"))
+}
+
+/// json error returned by the entrypoint instead of trapping the wasm
+pub fn error_response(err: &anyhow::Error) -> Value {
+    json!({ "success": false, "error": err.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::MemoryHost;
+
+    #[test]
+    fn test_parse_commands() {
+        let store = json!({ "payload": { "cmd": "store", "path": "/var/share/proof.bin" } });
+        assert_eq!(
+            EntrypointCmd::from_args(&store).unwrap(),
+            EntrypointCmd::Store {
+                path: "/var/share/proof.bin".to_string()
+            }
+        );
+
+        let append = json!({ "payload": { "cmd": "append" } });
+        assert_eq!(
+            EntrypointCmd::from_args(&append).unwrap(),
+            EntrypointCmd::Append
+        );
+
+        let latest = json!({ "payload": { "cmd": "latest" } });
+        assert_eq!(
+            EntrypointCmd::from_args(&latest).unwrap(),
+            EntrypointCmd::Latest
+        );
+
+        let query = json!({ "payload": { "cmd": "query", "block": 22000000 } });
+        assert_eq!(
+            EntrypointCmd::from_args(&query).unwrap(),
+            EntrypointCmd::Query {
+                holder: None,
+                block: Some(22_000_000)
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_malformed_commands() {
+        // missing payload
+        assert!(EntrypointCmd::from_args(&json!({ "proof": "" })).is_err());
+        // unknown command
+        assert!(EntrypointCmd::from_args(&json!({ "payload": { "cmd": "mint" } })).is_err());
+        // missing store path
+        assert!(EntrypointCmd::from_args(&json!({ "payload": { "cmd": "store" } })).is_err());
+        // missing command
+        assert!(EntrypointCmd::from_args(&json!({ "payload": {} })).is_err());
+    }
+
+    #[test]
+    fn test_history_append_writes_one_file_per_entry() {
+        let host = MemoryHost::new();
+        let args = json!({ "payload": { "cmd": "append" }, "proof": "AA==", "success": true });
+
+        for seq in 0..3 {
+            let response = EntrypointCmd::Append.execute(&host, &args).unwrap();
+            assert_eq!(response["seq"], seq);
+        }
+
+        assert_eq!(history_len(&host).unwrap(), 3);
+        assert_eq!(read_history_entry(&host, 2).unwrap().seq, 2);
+        // earlier entries are left untouched
+        let first = host.file(&history_entry_path(0)).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<HistoryEntry>(&first).unwrap().seq,
+            0
+        );
+        assert_eq!(history_entry_path(10), "/var/share/0000000a.hst");
+    }
+
+    #[test]
+    fn test_history_corrupted_entry() {
+        let host = MemoryHost::new();
+        EntrypointCmd::Append.execute(&host, &json!({})).unwrap();
+        host.set_storage_file(&history_entry_path(0), b"not json")
+            .unwrap();
+
+        assert!(EntrypointCmd::List.execute(&host, &json!({})).is_err());
+        assert!(EntrypointCmd::Latest.execute(&host, &json!({})).is_err());
+    }
+
+    #[test]
+    fn test_read_errors_are_not_empty_files() {
+        let host = MemoryHost::new();
+        assert!(read_storage_file(&host, HISTORY_PATH).unwrap().is_empty());
+
+        let host = MemoryHost::new().with_unreadable(HISTORY_PATH);
+        assert!(read_storage_file(&host, HISTORY_PATH).is_err());
+        assert!(EntrypointCmd::Append.execute(&host, &json!({})).is_err());
+        assert!(host.file(&history_entry_path(0)).is_none());
+    }
+}
@@ -0,0 +1,208 @@
+use alloc::{string::ToString as _, vec::Vec};
+use serde_json::Value;
+use valence_coprocessor_wasm::abi;
+
+#[cfg(any(test, feature = "memory-host"))]
+pub use memory::MemoryHost;
+
+/// latest validated block of a coprocessor domain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DomainBlock {
+    pub number: u64,
+    pub root: [u8; 32],
+}
+
+/// host functions the controller depends on. the coprocessor wasm
+/// runtime is one implementation; `MemoryHost`, behind the `memory-host`
+/// feature, replays recorded responses so the controller logic can run
+/// natively.
+pub trait ControllerHost {
+    /// appends a line to the request log
+    fn log(&self, msg: &str) -> anyhow::Result<()>;
+
+    /// returns the latest validated block of the coprocessor `domain`
+    fn get_latest_block(&self, domain: &str) -> anyhow::Result<Option<DomainBlock>>;
+
+    /// performs an alchemy json-rpc call on `network`
+    fn alchemy(&self, network: &str, method: &str, params: &Value) -> anyhow::Result<Value>;
+
+    /// reads a file from the controller virtual filesystem. files that
+    /// were never written are `None`; any other failure is an error.
+    fn get_storage_file(&self, path: &str) -> anyhow::Result<Option<Vec<u8>>>;
+
+    /// writes a file to the controller virtual filesystem
+    fn set_storage_file(&self, path: &str, contents: &[u8]) -> anyhow::Result<()>;
+}
+
+/// host backed by the coprocessor wasm abi
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WasmHost;
+
+impl ControllerHost for WasmHost {
+    fn log(&self, msg: &str) -> anyhow::Result<()> {
+        abi::log!("{msg}")
+    }
+
+    fn get_latest_block(&self, domain: &str) -> anyhow::Result<Option<DomainBlock>> {
+        Ok(abi::get_latest_block(domain)?.map(|block| DomainBlock {
+            number: block.number,
+            root: block.root,
+        }))
+    }
+
+    fn alchemy(&self, network: &str, method: &str, params: &Value) -> anyhow::Result<Value> {
+        abi::alchemy(network, method, params)
+    }
+
+    fn get_storage_file(&self, path: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        // the service reports files that were never written as errors
+        match abi::get_storage_file(path) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(e) if is_missing_file(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn set_storage_file(&self, path: &str, contents: &[u8]) -> anyhow::Result<()> {
+        abi::set_storage_file(path, contents)
+    }
+}
+
+/// error the coprocessor virtual filesystem, fatfs, reports when opening
+/// a file that was never written
+const MISSING_FILE_ERROR: &str = "No such file or directory";
+
+/// only the message of the host error crosses the wasm abi, so a missing
+/// file is told apart by its exact message. any other failure, including
+/// messages merely mentioning a missing entry, is an error.
+fn is_missing_file(err: &anyhow::Error) -> bool {
+    err.root_cause().to_string() == MISSING_FILE_ERROR
+}
+
+/// native host for tests and the recorder, never compiled into the wasm
+/// controller
+#[cfg(any(test, feature = "memory-host"))]
+mod memory {
+    use alloc::{
+        collections::{BTreeMap, BTreeSet},
+        string::{String, ToString as _},
+        vec::Vec,
+    };
+    use core::cell::RefCell;
+    use serde_json::Value;
+    use storage_proof_core::fixture::Fixture;
+
+    use super::{ControllerHost, DomainBlock};
+
+    /// in-memory host that replays recorded domain blocks and alchemy
+    /// responses, and keeps the virtual filesystem in a map.
+    #[derive(Debug, Default)]
+    pub struct MemoryHost {
+        /// block returned by `get_latest_block` for any domain
+        pub block: Option<DomainBlock>,
+        /// alchemy responses, keyed by json-rpc method
+        pub responses: BTreeMap<String, Value>,
+        /// alchemy requests received, as `(network, method, params)`
+        pub requests: RefCell<Vec<(String, String, Value)>>,
+        pub files: RefCell<BTreeMap<String, Vec<u8>>>,
+        /// paths whose reads fail, to exercise filesystem errors
+        pub unreadable: BTreeSet<String>,
+        pub logs: RefCell<Vec<String>>,
+    }
+
+    impl MemoryHost {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// replays a recorded fixture: its block is served as the latest
+        /// domain block and its proof as the `eth_getProof` response.
+        pub fn from_fixture(fixture: &Fixture) -> anyhow::Result<Self> {
+            Ok(Self::new()
+                .with_block(fixture.block.number, fixture.block.root.0)
+                .with_response("eth_getProof", serde_json::to_value(&fixture.proof)?))
+        }
+
+        pub fn with_block(mut self, number: u64, root: [u8; 32]) -> Self {
+            self.block = Some(DomainBlock { number, root });
+            self
+        }
+
+        pub fn with_response(mut self, method: &str, response: Value) -> Self {
+            self.responses.insert(method.to_string(), response);
+            self
+        }
+
+        pub fn with_unreadable(mut self, path: &str) -> Self {
+            self.unreadable.insert(path.to_string());
+            self
+        }
+
+        /// returns the contents of a file, if it was ever written
+        pub fn file(&self, path: &str) -> Option<Vec<u8>> {
+            self.files.borrow().get(path).cloned()
+        }
+    }
+
+    impl ControllerHost for MemoryHost {
+        fn log(&self, msg: &str) -> anyhow::Result<()> {
+            self.logs.borrow_mut().push(msg.to_string());
+            Ok(())
+        }
+
+        fn get_latest_block(&self, _domain: &str) -> anyhow::Result<Option<DomainBlock>> {
+            Ok(self.block)
+        }
+
+        fn alchemy(&self, network: &str, method: &str, params: &Value) -> anyhow::Result<Value> {
+            self.requests.borrow_mut().push((
+                network.to_string(),
+                method.to_string(),
+                params.clone(),
+            ));
+
+            self.responses
+                .get(method)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("no recorded response for {method}"))
+        }
+
+        fn get_storage_file(&self, path: &str) -> anyhow::Result<Option<Vec<u8>>> {
+            anyhow::ensure!(
+                !self.unreadable.contains(path),
+                "failed to read {path}: i/o error"
+            );
+
+            Ok(self.file(path))
+        }
+
+        fn set_storage_file(&self, path: &str, contents: &[u8]) -> anyhow::Result<()> {
+            self.files
+                .borrow_mut()
+                .insert(path.to_string(), contents.to_vec());
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_missing_file() {
+        assert!(is_missing_file(&anyhow::anyhow!(MISSING_FILE_ERROR)));
+        assert!(is_missing_file(
+            &anyhow::anyhow!(MISSING_FILE_ERROR).context("failed to read /var/share/history.log")
+        ));
+
+        for message in [
+            "path not found in FAT",
+            "not found",
+            "Corrupted file system: No such file or directory",
+            "no such file or directory",
+        ] {
+            assert!(!is_missing_file(&anyhow::anyhow!(message)), "{message}");
+        }
+    }
+}
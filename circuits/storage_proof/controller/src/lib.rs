@@ -1,18 +1,29 @@
 #![cfg_attr(not(test), no_std)]
 extern crate alloc;
 
+pub mod archive;
+pub mod commands;
+pub mod host;
+
 use core::str::FromStr;
 
-use alloc::{format, string::ToString as _, vec::Vec};
+use alloc::{
+    format,
+    string::{String, ToString as _},
+    vec::Vec,
+};
 use alloy_primitives::{hex, Address};
 use alloy_rpc_types_eth::EIP1186AccountProofResponse;
+use archive::IndexEntry;
+use commands::{error_response, EntrypointCmd};
+use host::{ControllerHost, WasmHost};
 use serde_json::{json, Value};
-use storage_proof_core::{proof::mapping_slot_key, ControllerInputs};
+use storage_proof_core::{
+    consts::{alchemy_network, coprocessor_domain, is_allowlisted_erc20, BALANCE_SLOT},
+    proof::mapping_slot_key,
+    ControllerInputs,
+};
 use valence_coprocessor::{StateProof, Witness};
-use valence_coprocessor_wasm::abi;
-
-const NETWORK: &str = "eth-mainnet";
-const DOMAIN: &str = "ethereum-electra-alpha";
 
 // This component contains off-chain logic executed as Wasm within the
 // Valence ZK Coprocessor's sandboxed environment.
@@ -30,41 +41,107 @@ const DOMAIN: &str = "ethereum-electra-alpha";
 //
 // expects ControllerInputs serialized as json
 pub fn get_witnesses(args: Value) -> anyhow::Result<Vec<Witness>> {
+    get_witnesses_with(&WasmHost, args)
+}
+
+/// computes the circuit witnesses using the given host functions, and
+/// records the request in the proof index
+pub fn get_witnesses_with<H: ControllerHost>(
+    host: &H,
+    args: Value,
+) -> anyhow::Result<Vec<Witness>> {
     let args_pretty = serde_json::to_string_pretty(&args)?;
-    abi::log!("received a proof request with arguments {args_pretty}")?;
+    host.log(&format!(
+        "received a proof request with arguments {args_pretty}"
+    ))?;
 
     let witness_inputs: ControllerInputs = serde_json::from_value(args)?;
+    let prepared = prepare_witnesses(host, &witness_inputs)?;
+
+    // index the request so the delivered proof can be archived under
+    // a path derived from the holder and block number
+    let mut index = archive::load_index(host)?;
+    let position = index.record(prepared.entry)?;
+    archive::save_entry(host, &index, position)?;
+
+    Ok(prepared.witnesses)
+}
+
+/// circuit witnesses together with the proof index entry describing them
+pub struct PreparedWitnesses {
+    pub witnesses: Vec<Witness>,
+    pub entry: IndexEntry,
+}
+
+/// fetches the account state proof at the latest domain block and
+/// builds the circuit witnesses, without side effects on the filesystem
+pub fn prepare_witnesses<H: ControllerHost>(
+    host: &H,
+    witness_inputs: &ControllerInputs,
+) -> anyhow::Result<PreparedWitnesses> {
     let erc20_addr = Address::from_str(&witness_inputs.erc20)?;
     let eth_addr = Address::from_str(&witness_inputs.eth_addr)?;
 
-    let block =
-        abi::get_latest_block(DOMAIN)?.ok_or_else(|| anyhow::anyhow!("no valid domain block"))?;
+    // alchemy network and coprocessor domain of the network profile the
+    // circuit params select
+    let network = alchemy_network();
+    let domain = coprocessor_domain();
+
+    let block = host
+        .get_latest_block(domain)?
+        .ok_or_else(|| anyhow::anyhow!("no valid domain block"))?;
 
     let root = block.root;
-    abi::log!("root: {}", hex::encode(root))?;
+    host.log(&format!("root: {}", hex::encode(root)))?;
+
+    let block_number = block.number;
+    let block = format!("{:#x}", block_number);
 
-    let block = format!("{:#x}", block.number);
+    anyhow::ensure!(
+        is_allowlisted_erc20(&erc20_addr.into_array()),
+        "erc20 {erc20_addr} is not allowlisted by the circuit"
+    );
 
-    // 9 for usdc, possibly different index for most erc20
-    let slot_key = mapping_slot_key(eth_addr, 9u64);
+    // balances mapping slot the circuit was built for; 9 for usdc
+    let slot_key = mapping_slot_key(eth_addr, BALANCE_SLOT);
     let slot_key = format!("{:#x}", slot_key);
 
-    abi::log!("storage key = {slot_key}")?;
+    host.log(&format!("storage key = {slot_key}"))?;
 
-    let proof = abi::alchemy(
-        NETWORK,
+    let proof = host.alchemy(
+        network,
         "eth_getProof",
         &json!([erc20_addr, [slot_key], block]),
     )?;
 
     let proof: EIP1186AccountProofResponse = serde_json::from_value(proof)?;
-    abi::log!("proof: {}", serde_json::to_string_pretty(&proof)?)?;
+    host.log(&format!("proof: {}", serde_json::to_string_pretty(&proof)?))?;
+
+    let amount = proof
+        .storage_proof
+        .first()
+        .map(|p| p.value)
+        .unwrap_or_default();
+    let entry = IndexEntry {
+        holder: witness_inputs.eth_addr.clone(),
+        erc20: witness_inputs.erc20.clone(),
+        block: block_number,
+        root: hex::encode_prefixed(root),
+        amount: amount.to_string(),
+        recipient: witness_inputs.neutron_addr.clone(),
+        request: witness_inputs.request.clone(),
+        // assigned by the index when the request is recorded
+        path: String::new(),
+        archived: false,
+    };
+
     let proof = serde_json::to_vec(&proof)?;
 
+    // informational, the circuit does not read the unverified payload
     let state_proof = StateProof {
-        domain: DOMAIN.into(),
+        domain: domain.into(),
         root,
-        payload: Default::default(),
+        payload: block_number.to_be_bytes().to_vec(),
         proof,
     };
 
@@ -73,30 +150,242 @@ pub fn get_witnesses(args: Value) -> anyhow::Result<Vec<Witness>> {
         Witness::StateProof(state_proof),
         // witness 1: neutron addr (destination)
         Witness::Data(witness_inputs.neutron_addr.as_bytes().to_vec()),
+        // witness 2: eth addr (balance holder)
+        Witness::Data(eth_addr.to_vec()),
     ]
     .to_vec();
 
-    Ok(witnesses)
+    Ok(PreparedWitnesses { witnesses, entry })
 }
 
 pub fn entrypoint(args: Value) -> anyhow::Result<Value> {
-    abi::log!(
+    entrypoint_with(&WasmHost, args)
+}
+
+/// handles an entrypoint request using the given host functions
+pub fn entrypoint_with<H: ControllerHost>(host: &H, args: Value) -> anyhow::Result<Value> {
+    host.log(&format!(
         "received an entrypoint request with arguments {}",
         serde_json::to_string(&args).unwrap_or_default()
-    )?;
+    ))?;
 
-    let cmd = args["payload"]["cmd"].as_str().unwrap();
+    // malformed payloads are reported back as json rather than trapping the wasm
+    match EntrypointCmd::from_args(&args).and_then(|cmd| cmd.execute(host, &args)) {
+        Ok(response) => Ok(response),
+        Err(e) => {
+            host.log(&format!("entrypoint request failed: {e}"))?;
+            Ok(error_response(&e))
+        }
+    }
+}
 
-    match cmd {
-        "store" => {
-            let path = args["payload"]["path"].as_str().unwrap().to_string();
-            let bytes = serde_json::to_vec(&args).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{archive::ARCHIVE_INDEX_PATH, commands::HISTORY_PATH, host::MemoryHost};
+    use storage_proof_core::fixture::Fixture;
 
-            abi::set_storage_file(&path, &bytes).unwrap();
-        }
+    const USDC_FIXTURE: &[u8] = include_bytes!("../../core/fixtures/usdc.json");
+
+    fn fixture() -> Fixture {
+        Fixture::from_slice(USDC_FIXTURE).unwrap()
+    }
+
+    fn inputs() -> Value {
+        serde_json::to_value(fixture().inputs).unwrap()
+    }
+
+    fn host() -> MemoryHost {
+        MemoryHost::from_fixture(&fixture()).unwrap()
+    }
+
+    #[test]
+    fn test_get_witnesses() {
+        let host = host();
+        let witnesses = get_witnesses_with(&host, inputs()).unwrap();
+
+        assert_eq!(witnesses.len(), 3);
+
+        let fixture = fixture();
+        let state_proof = witnesses[0].as_state_proof().unwrap();
+        assert_eq!(state_proof.root, fixture.block.root.0);
+        assert_eq!(state_proof.domain, coprocessor_domain());
+        assert_eq!(state_proof.payload, fixture.block.number.to_be_bytes());
+        assert_eq!(
+            witnesses[1].as_data().unwrap(),
+            fixture.inputs.neutron_addr.as_bytes()
+        );
+        assert_eq!(
+            witnesses[2].as_data().unwrap(),
+            Address::from_str(&fixture.inputs.eth_addr)
+                .unwrap()
+                .as_slice()
+        );
+
+        // the requested storage key must be the one the fixture was recorded with
+        let requests = host.requests.borrow();
+        assert_eq!(requests[0].0, alchemy_network());
+        assert_eq!(
+            requests[0].2[1][0],
+            json!(format!(
+                "{:#x}",
+                fixture.proof.storage_proof[0].key.as_b256()
+            ))
+        );
+        assert_eq!(
+            requests[0].2[2],
+            json!(format!("{:#x}", fixture.block.number))
+        );
+    }
+
+    #[test]
+    fn test_get_witnesses_records_index_entry() {
+        let host = host();
+        get_witnesses_with(&host, inputs()).unwrap();
+
+        let fixture = fixture();
+        let index = archive::load_index(&host).unwrap();
+        let entry = &index.query(Some(&fixture.inputs.eth_addr), Some(fixture.block.number))[0];
 
-        _ => panic!("unknown entrypoint command"),
+        assert_eq!(entry.amount, "7191271");
+        assert_eq!(entry.recipient, fixture.inputs.neutron_addr);
+        assert_eq!(entry.root, hex::encode_prefixed(fixture.block.root));
+        assert!(!entry.archived);
     }
 
-    Ok(args)
+    #[test]
+    fn test_get_witnesses_unreadable_index() {
+        // an unreadable index must not be replaced by a fresh one
+        let host = host().with_unreadable(ARCHIVE_INDEX_PATH);
+
+        assert!(get_witnesses_with(&host, inputs()).is_err());
+        assert!(host.file(ARCHIVE_INDEX_PATH).is_none());
+    }
+
+    #[test]
+    fn test_get_witnesses_without_domain_block() {
+        let host = MemoryHost::new();
+
+        assert!(get_witnesses_with(&host, inputs()).is_err());
+    }
+
+    #[test]
+    fn test_get_witnesses_invalid_inputs() {
+        let host = host();
+
+        let mut invalid_erc20 = inputs();
+        invalid_erc20["erc20"] = json!("0x00");
+
+        let mut not_allowlisted = inputs();
+        not_allowlisted["erc20"] = json!("0x0000000000000000000000000000000000000001");
+
+        assert!(get_witnesses_with(&host, json!({ "erc20": "0x00" })).is_err());
+        assert!(get_witnesses_with(&host, invalid_erc20).is_err());
+        assert!(get_witnesses_with(&host, not_allowlisted).is_err());
+    }
+
+    #[test]
+    fn test_entrypoint_rejects_preview() {
+        let host = host();
+        let fixture = fixture();
+
+        // previews are run natively by the recorder, not delivered proofs
+        let preview = json!({ "payload": { "cmd": "preview", "inputs": fixture.inputs } });
+        let response = entrypoint_with(&host, preview).unwrap();
+        assert_eq!(response["success"], false);
+    }
+
+    #[test]
+    fn test_entrypoint_store() {
+        let host = host();
+        let args = json!({
+            "payload": { "cmd": "store", "path": "/var/share/proof.bin" },
+            "proof": "AA==",
+            "success": true,
+        });
+
+        let response = entrypoint_with(&host, args.clone()).unwrap();
+
+        assert_eq!(response, args);
+        assert_eq!(
+            host.file("/var/share/proof.bin").unwrap(),
+            serde_json::to_vec(&args).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_entrypoint_history() {
+        let host = host();
+        let args = json!({ "payload": { "cmd": "append" }, "proof": "AA==", "success": true });
+
+        entrypoint_with(&host, args.clone()).unwrap();
+        let response = entrypoint_with(&host, args).unwrap();
+        assert_eq!(response["seq"], 1);
+
+        let list = entrypoint_with(&host, json!({ "payload": { "cmd": "list" } })).unwrap();
+        assert_eq!(list["entries"].as_array().unwrap().len(), 2);
+
+        let latest = entrypoint_with(&host, json!({ "payload": { "cmd": "latest" } })).unwrap();
+        assert_eq!(latest["seq"], 1);
+        assert_eq!(latest["args"]["proof"], "AA==");
+
+        let delete = json!({ "payload": { "cmd": "delete", "path": HISTORY_PATH } });
+        entrypoint_with(&host, delete).unwrap();
+        let latest = entrypoint_with(&host, json!({ "payload": { "cmd": "latest" } })).unwrap();
+        assert!(latest.is_null());
+    }
+
+    #[test]
+    fn test_entrypoint_archive() {
+        let host = host();
+        let fixture = fixture();
+        let mut request = inputs();
+        request["request"] = json!("r1");
+        get_witnesses_with(&host, request).unwrap();
+
+        let holder = fixture.inputs.eth_addr;
+        let mut args = json!({
+            "payload": { "cmd": "archive", "holder": holder, "request": "r1" },
+            "proof": "AA==",
+            "success": false,
+        });
+
+        // failed deliveries leave the request pending
+        let response = entrypoint_with(&host, args.clone()).unwrap();
+        assert_eq!(response["success"], false);
+
+        args["success"] = json!(true);
+        let entry = entrypoint_with(&host, args.clone()).unwrap();
+
+        let index = archive::load_index(&host).unwrap();
+        let path = &index.query(Some(&holder), Some(fixture.block.number))[0].path;
+        assert_eq!(&entry["path"], path);
+        assert_eq!(entry["request"], "r1");
+        assert_eq!(
+            host.file(path.as_str()).unwrap(),
+            serde_json::to_vec(&args).unwrap()
+        );
+
+        let query = json!({ "payload": { "cmd": "query", "holder": holder } });
+        let response = entrypoint_with(&host, query).unwrap();
+        assert_eq!(response["entries"][0]["archived"], true);
+        assert!(host.file(ARCHIVE_INDEX_PATH).is_some());
+    }
+
+    #[test]
+    fn test_entrypoint_malformed_payload() {
+        let host = host();
+
+        let response = entrypoint_with(&host, json!({ "payload": { "cmd": "mint" } })).unwrap();
+        assert_eq!(response["success"], false);
+        assert!(response["error"].is_string());
+
+        // nothing pending for this holder
+        let archive = json!({
+            "payload": { "cmd": "archive", "holder": inputs()["eth_addr"], "request": "r1" },
+            "success": true,
+        });
+        let response = entrypoint_with(&host, archive).unwrap();
+        assert!(response["error"].is_string());
+    }
 }
@@ -0,0 +1,104 @@
+// Compiles the circuit parameters into `consts`.
+extern crate alloc;
+
+// only the profile lookup is used here
+#[allow(dead_code)]
+#[path = "src/network.rs"]
+mod network;
+#[path = "src/params.rs"]
+mod params;
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use params::CircuitParams;
+
+/// parameters file to build with, relative paths resolve from the workspace root
+const PARAMS_PATH_ENV: &str = "CIRCUIT_PARAMS";
+/// untracked parameters file written by the deploy script, relative to the
+/// workspace root
+const DEPLOYED_PARAMS_PATH: &str = "artifacts/circuit_params.toml";
+/// tracked development parameters, used when neither of the above exists
+const DEFAULT_PARAMS_PATH: &str = "default_params.toml";
+
+fn main() -> anyhow::Result<()> {
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR")?);
+    let params_path = params_path(&manifest_dir);
+
+    println!("cargo:rerun-if-env-changed={PARAMS_PATH_ENV}");
+    println!("cargo:rerun-if-changed={}", params_path.display());
+    println!(
+        "cargo:rerun-if-changed={}",
+        manifest_dir
+            .join("../../..")
+            .join(DEPLOYED_PARAMS_PATH)
+            .display()
+    );
+    println!("cargo:rerun-if-changed=src/params.rs");
+    println!("cargo:rerun-if-changed=src/network.rs");
+
+    let contents = fs::read_to_string(&params_path)
+        .map_err(|e| anyhow::anyhow!("failed to read circuit params from {params_path:?}: {e}"))?;
+    let params: CircuitParams = toml::from_str(&contents)?;
+
+    params
+        .validate()
+        .map_err(|e| anyhow::anyhow!("invalid circuit params in {params_path:?}: {e}"))?;
+
+    let allowlist = params
+        .erc20_allowlist_bytes()?
+        .iter()
+        .map(|addr| format!("{addr:?}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let generated = format!(
+        "// generated by build.rs from {params_path:?}\n\
+         pub const NETWORK: &str = {:?};\n\
+         pub const CW20_ADDR: &str = {:?};\n\
+         pub const ERC20_ALLOWLIST: &[[u8; 20]] = &[{allowlist}];\n\
+         pub const ERC20_DECIMALS: u8 = {};\n\
+         pub const CW20_DECIMALS: u8 = {};\n\
+         pub const BALANCE_SLOT: u64 = {};\n\
+         pub const PARAMS_HASH: [u8; 32] = {:?};\n",
+        params.network,
+        params.cw20_addr,
+        params.erc20_decimals,
+        params.cw20_decimals,
+        params.balance_slot,
+        params.hash(),
+    );
+
+    let out_path = PathBuf::from(env::var("OUT_DIR")?).join("params.rs");
+    fs::write(out_path, generated)?;
+
+    Ok(())
+}
+
+/// the development defaults under the `dev-params` feature, else
+/// `$CIRCUIT_PARAMS`, else the deployed params, else the development defaults
+fn params_path(manifest_dir: &Path) -> PathBuf {
+    let workspace_root = manifest_dir.join("../../..");
+
+    // the fixtures and golden files are recorded against the defaults
+    if env::var_os("CARGO_FEATURE_DEV_PARAMS").is_some() {
+        return manifest_dir.join(DEFAULT_PARAMS_PATH);
+    }
+
+    if let Ok(path) = env::var(PARAMS_PATH_ENV) {
+        return workspace_root.join(path);
+    }
+
+    let deployed = workspace_root.join(DEPLOYED_PARAMS_PATH);
+    if deployed.exists() {
+        return deployed;
+    }
+
+    println!(
+        "cargo:warning=no {PARAMS_PATH_ENV} set nor {DEPLOYED_PARAMS_PATH} deployed, \
+         building with the development params of {DEFAULT_PARAMS_PATH}"
+    );
+    manifest_dir.join(DEFAULT_PARAMS_PATH)
+}
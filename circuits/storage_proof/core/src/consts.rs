@@ -1,2 +1,30 @@
-// DO NOT EDIT. This file is automatically generated by deploy_coprocessor_app.rs.
-pub const CW20_ADDR: &str = "neutron15s4cwrsqu6np228f759kh5az7dups6wyyanldadhd52z9ltyvpkq00sy2p";
\ No newline at end of file
+// Circuit parameters, compiled in by build.rs from the file at `$CIRCUIT_PARAMS`,
+// the untracked `artifacts/circuit_params.toml` written by the deploy script,
+// or the development defaults in `default_params.toml`.
+include!(concat!(env!("OUT_DIR"), "/params.rs"));
+
+use crate::network::NetworkProfile;
+
+/// profile of the network the circuit was built for
+pub fn network_profile() -> &'static NetworkProfile {
+    NetworkProfile::by_name(NETWORK).expect("network profile is validated by build.rs")
+}
+
+/// alchemy network the controller requests proofs on
+pub fn alchemy_network() -> &'static str {
+    network_profile()
+        .alchemy_network
+        .expect("alchemy network is validated by build.rs")
+}
+
+/// coprocessor domain the state proofs are opened against
+pub fn coprocessor_domain() -> &'static str {
+    network_profile()
+        .coprocessor_domain
+        .expect("coprocessor domain is validated by build.rs")
+}
+
+/// whether the circuit accepts balance proofs of the `erc20` contract
+pub fn is_allowlisted_erc20(erc20: &[u8; 20]) -> bool {
+    ERC20_ALLOWLIST.contains(erc20)
+}
@@ -0,0 +1,93 @@
+use alloc::string::String;
+use alloy_primitives::B256;
+use alloy_rpc_types_eth::EIP1186AccountProofResponse;
+use serde::{Deserialize, Serialize};
+
+use crate::ControllerInputs;
+
+/// current version of the fixture file format
+pub const FIXTURE_VERSION: u32 = 1;
+
+/// recorded `eth_getProof` response together with the domain block it
+/// was taken at and the controller inputs that request it. fixtures are
+/// written by the `recorder` binary and replayed by tests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fixture {
+    /// fixture file format version
+    pub version: u32,
+    /// what the fixture covers
+    pub description: String,
+    /// alchemy network the proof was requested on
+    pub network: String,
+    /// coprocessor domain the block belongs to
+    pub domain: String,
+    pub block: FixtureBlock,
+    /// controller inputs requesting the proof
+    pub inputs: ControllerInputs,
+    /// erc20 balances mapping slot index
+    pub slot_index: u64,
+    /// raw `eth_getProof` response
+    pub proof: EIP1186AccountProofResponse,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FixtureBlock {
+    pub number: u64,
+    pub root: B256,
+}
+
+impl Fixture {
+    /// parses a fixture file, rejecting unsupported format versions
+    pub fn from_slice(bytes: &[u8]) -> anyhow::Result<Self> {
+        let fixture: Self = serde_json::from_slice(bytes)?;
+
+        anyhow::ensure!(
+            fixture.version == FIXTURE_VERSION,
+            "unsupported fixture version {}, expected {FIXTURE_VERSION}",
+            fixture.version
+        );
+
+        Ok(fixture)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USDC_FIXTURE: &[u8] = include_bytes!("../fixtures/usdc.json");
+
+    #[test]
+    fn test_parse_fixture() {
+        let fixture = Fixture::from_slice(USDC_FIXTURE).unwrap();
+
+        assert_eq!(fixture.slot_index, 9);
+        assert_eq!(fixture.proof.storage_proof.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_synthetic_fixtures() {
+        let zero = Fixture::from_slice(include_bytes!("../fixtures/zero_balance.json")).unwrap();
+        assert!(zero.proof.storage_proof[0].value.is_zero());
+
+        let proxy = Fixture::from_slice(include_bytes!("../fixtures/proxy.json")).unwrap();
+        assert_eq!(proxy.proof.storage_proof.len(), 1);
+
+        let multi = Fixture::from_slice(include_bytes!("../fixtures/multi_slot.json")).unwrap();
+        assert_eq!(multi.proof.storage_proof.len(), 2);
+
+        let other =
+            Fixture::from_slice(include_bytes!("../fixtures/not_allowlisted.json")).unwrap();
+        assert_eq!(other.slot_index, 2);
+    }
+
+    #[test]
+    fn test_parse_fixture_unsupported_version() {
+        let mut fixture: serde_json::Value = serde_json::from_slice(USDC_FIXTURE).unwrap();
+        fixture["version"] = (FIXTURE_VERSION + 1).into();
+
+        let bytes = serde_json::to_vec(&fixture).unwrap();
+
+        assert!(Fixture::from_slice(&bytes).is_err());
+    }
+}
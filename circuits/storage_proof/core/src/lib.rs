@@ -3,11 +3,20 @@
 extern crate alloc;
 
 pub mod consts;
+pub mod fixture;
+pub mod mint;
+pub mod network;
+pub mod output;
+pub mod params;
 pub mod proof;
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct ControllerInputs {
     pub erc20: alloc::string::String,
     pub eth_addr: alloc::string::String,
     pub neutron_addr: alloc::string::String,
+    /// identifier chosen by the requester, echoed by the `archive`
+    /// entrypoint payload to select the proof request being delivered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<alloc::string::String>,
 }
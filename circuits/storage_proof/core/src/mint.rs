@@ -0,0 +1,39 @@
+use alloc::{string::ToString as _, vec::Vec};
+use valence_authorization_utils::{
+    authorization::{AtomicSubroutine, Subroutine},
+    authorization_message::{Message, MessageDetails, MessageType},
+    domain::Domain,
+    function::AtomicFunction,
+};
+use valence_library_utils::LibraryAccountType;
+
+/// name of the cw20 execute message both authorizations allow
+pub const CW20_MINT_MSG_NAME: &str = "mint";
+
+/// builds the function calling `mint` on the cw20 at `cw20_addr`.
+///
+/// the on-chain authorization and the zk message emitted by the circuit
+/// must describe the exact same function, otherwise every proof gets
+/// rejected; both are built from here.
+pub fn cw20_mint_function(cw20_addr: &str) -> AtomicFunction {
+    AtomicFunction {
+        domain: Domain::Main,
+        message_details: MessageDetails {
+            message_type: MessageType::CosmwasmExecuteMsg,
+            message: Message {
+                name: CW20_MINT_MSG_NAME.to_string(),
+                params_restrictions: None,
+            },
+        },
+        contract_address: LibraryAccountType::Addr(cw20_addr.to_string()),
+    }
+}
+
+/// builds the atomic subroutine executing a single cw20 mint
+pub fn cw20_mint_subroutine(cw20_addr: &str) -> Subroutine {
+    Subroutine::Atomic(AtomicSubroutine {
+        functions: Vec::from([cw20_mint_function(cw20_addr)]),
+        retry_logic: None,
+        expiration_time: None,
+    })
+}
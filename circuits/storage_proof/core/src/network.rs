@@ -0,0 +1,83 @@
+// shared with the build script, which checks the selected profile exists.
+// keep it free of crate-local imports.
+
+/// chain constants of a deployment target. fields that are not known for
+/// a network are `None`, and must then be provided in the deploy inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkProfile {
+    /// name the profile is selected by
+    pub name: &'static str,
+    pub neutron_chain_id: &'static str,
+    pub grpc_url: Option<&'static str>,
+    pub grpc_port: Option<&'static str>,
+    /// valence verification gateway the zk authorizations are verified by
+    pub verification_gateway: Option<&'static str>,
+    pub code_ids: Option<NetworkCodeIds>,
+    /// alchemy network the controller requests ethereum proofs on
+    pub alchemy_network: Option<&'static str>,
+    /// coprocessor domain the ethereum state roots are validated in
+    pub coprocessor_domain: Option<&'static str>,
+}
+
+/// code ids of the valence contracts stored on a network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkCodeIds {
+    pub authorizations: u64,
+    pub processor: u64,
+    pub cw20: u64,
+}
+
+/// neutron-1, proving ethereum mainnet balances
+pub const MAINNET: NetworkProfile = NetworkProfile {
+    name: "mainnet",
+    neutron_chain_id: "neutron-1",
+    grpc_url: Some("http://rpc.neutron.quokkastake.io"),
+    grpc_port: Some("9090"),
+    verification_gateway: Some(
+        "neutron1l3fgzcqse0xw84hdpytg7vcp04kcdm95wes2zd6ap8kpujmv9cwsv45wwk",
+    ),
+    code_ids: Some(NetworkCodeIds {
+        authorizations: 3937,
+        processor: 3934,
+        cw20: 4128,
+    }),
+    alchemy_network: Some("eth-mainnet"),
+    coprocessor_domain: Some("ethereum-electra-alpha"),
+};
+
+/// local neutron node, proving ethereum mainnet balances through the
+/// public coprocessor
+pub const LOCAL: NetworkProfile = NetworkProfile {
+    name: "local",
+    neutron_chain_id: "localneutron-1",
+    grpc_url: Some("http://localhost"),
+    grpc_port: Some("9090"),
+    verification_gateway: None,
+    code_ids: None,
+    alchemy_network: Some("eth-mainnet"),
+    coprocessor_domain: Some("ethereum-electra-alpha"),
+};
+
+pub const PROFILES: &[NetworkProfile] = &[MAINNET, LOCAL];
+
+impl NetworkProfile {
+    pub fn by_name(name: &str) -> Option<&'static NetworkProfile> {
+        PROFILES.iter().find(|profile| profile.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_by_name() {
+        assert_eq!(NetworkProfile::by_name("mainnet"), Some(&MAINNET));
+        assert_eq!(
+            NetworkProfile::by_name("local").unwrap().neutron_chain_id,
+            "localneutron-1"
+        );
+        assert!(NetworkProfile::by_name("testnet").is_none());
+        assert!(NetworkProfile::by_name("neutron-1").is_none());
+    }
+}
@@ -0,0 +1,34 @@
+use anyhow::ensure;
+
+/// length of the coprocessor root the program proof public inputs start
+/// with, followed by the circuit output
+pub const COPROCESSOR_ROOT_LEN: usize = 32;
+
+/// splits program proof public inputs into the coprocessor root and the
+/// circuit output, the zk message json the authorization contract parses
+pub fn split_public_inputs(inputs: &[u8]) -> anyhow::Result<(&[u8], &[u8])> {
+    ensure!(
+        inputs.len() > COPROCESSOR_ROOT_LEN,
+        "public inputs of {} bytes hold no circuit output after the coprocessor root",
+        inputs.len()
+    );
+
+    Ok(inputs.split_at(COPROCESSOR_ROOT_LEN))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_public_inputs() {
+        let mut inputs = [7u8; COPROCESSOR_ROOT_LEN].to_vec();
+        inputs.extend_from_slice(b"{}");
+
+        let (root, output) = split_public_inputs(&inputs).unwrap();
+        assert_eq!(root, [7u8; COPROCESSOR_ROOT_LEN]);
+        assert_eq!(output, b"{}");
+
+        assert!(split_public_inputs(&[0u8; COPROCESSOR_ROOT_LEN]).is_err());
+    }
+}
@@ -0,0 +1,196 @@
+// shared between the crate and its build script, which validates the
+// parameters and compiles them into `consts`. keep it free of crate-local
+// imports other than `network`, which the build script includes as well.
+use alloc::{string::String, vec::Vec};
+use anyhow::{anyhow, ensure};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::network::NetworkProfile;
+
+/// bech32 prefix of neutron addresses
+pub const NEUTRON_HRP: &str = "neutron";
+
+/// cw20-base rejects tokens with more decimals than this
+pub const MAX_CW20_DECIMALS: u8 = 18;
+
+/// parameters compiled into the circuit and controller. the deploy
+/// script writes them next to the strategy config, untracked.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitParams {
+    /// name of the `NetworkProfile` the controller requests proofs on
+    pub network: String,
+    /// cw20 contract the zk message mints on
+    pub cw20_addr: String,
+    /// erc20 contracts the circuit accepts balance proofs of
+    pub erc20_allowlist: Vec<String>,
+    /// decimals of the allowlisted erc20 tokens
+    pub erc20_decimals: u8,
+    /// decimals of the cw20 mirror token
+    pub cw20_decimals: u8,
+    /// index of the erc20 balances mapping storage slot
+    pub balance_slot: u64,
+}
+
+impl CircuitParams {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let profile = self.network_profile()?;
+        ensure!(
+            profile.alchemy_network.is_some() && profile.coprocessor_domain.is_some(),
+            "network {} has no alchemy network or coprocessor domain to prove balances on",
+            self.network
+        );
+
+        let (hrp, data) = bech32::decode(&self.cw20_addr)
+            .map_err(|e| anyhow!("invalid cw20 addr {}: {e}", self.cw20_addr))?;
+        ensure!(
+            hrp.as_str() == NEUTRON_HRP,
+            "cw20 addr must be a neutron address, got prefix {}",
+            hrp.as_str()
+        );
+        ensure!(
+            data.len() == 32,
+            "cw20 addr must be a 32 byte contract address, got {} bytes",
+            data.len()
+        );
+
+        ensure!(
+            !self.erc20_allowlist.is_empty(),
+            "erc20 allowlist must not be empty"
+        );
+        let allowlist = self.erc20_allowlist_bytes()?;
+        for (i, addr) in allowlist.iter().enumerate() {
+            ensure!(
+                !allowlist[..i].contains(addr),
+                "duplicate erc20 allowlist entry {}",
+                self.erc20_allowlist[i]
+            );
+        }
+
+        ensure!(
+            self.cw20_decimals <= MAX_CW20_DECIMALS,
+            "cw20 decimals must not exceed {MAX_CW20_DECIMALS}, got {}",
+            self.cw20_decimals
+        );
+        // the circuit mints the proven balance as is
+        ensure!(
+            self.erc20_decimals == self.cw20_decimals,
+            "cw20 decimals ({}) must equal the erc20 decimals ({})",
+            self.cw20_decimals,
+            self.erc20_decimals
+        );
+
+        Ok(())
+    }
+
+    pub fn network_profile(&self) -> anyhow::Result<&'static NetworkProfile> {
+        NetworkProfile::by_name(&self.network)
+            .ok_or_else(|| anyhow!("unknown network profile {}", self.network))
+    }
+
+    /// decodes the `0x` prefixed hex allowlist entries
+    pub fn erc20_allowlist_bytes(&self) -> anyhow::Result<Vec<[u8; 20]>> {
+        self.erc20_allowlist
+            .iter()
+            .map(|addr| decode_eth_addr(addr))
+            .collect()
+    }
+
+    /// sha256 of the canonical json encoding of the parameters, with
+    /// the allowlist lowercased. committed to by the circuit output.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut canonical = self.clone();
+        for addr in canonical.erc20_allowlist.iter_mut() {
+            *addr = addr.to_lowercase();
+        }
+
+        let encoded = serde_json::to_vec(&canonical).expect("params are always serializable");
+
+        Sha256::digest(encoded).into()
+    }
+}
+
+fn decode_eth_addr(addr: &str) -> anyhow::Result<[u8; 20]> {
+    let hex = addr
+        .strip_prefix("0x")
+        .ok_or_else(|| anyhow!("erc20 addr {addr} must be 0x prefixed"))?;
+    ensure!(
+        hex.len() == 40 && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        "erc20 addr {addr} must be 20 hex encoded bytes"
+    );
+
+    let mut bytes = [0u8; 20];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)?;
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{string::ToString as _, vec};
+
+    fn params() -> CircuitParams {
+        CircuitParams {
+            network: "mainnet".to_string(),
+            cw20_addr: "neutron15s4cwrsqu6np228f759kh5az7dups6wyyanldadhd52z9ltyvpkq00sy2p"
+                .to_string(),
+            erc20_allowlist: vec!["0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string()],
+            erc20_decimals: 6,
+            cw20_decimals: 6,
+            balance_slot: 9,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        params().validate().unwrap();
+    }
+
+    #[test]
+    fn test_validate_rejects_invalid_params() {
+        // account, not contract, address
+        let mut p = params();
+        p.cw20_addr = "neutron1m6w8n0hluq7avn40hj0n6jnj8ejhykfrwfnnjh".to_string();
+        assert!(p.validate().is_err());
+
+        let mut p = params();
+        p.network = "neutron-1".to_string();
+        assert!(p.validate().is_err());
+
+        let mut p = params();
+        p.erc20_allowlist = vec![];
+        assert!(p.validate().is_err());
+
+        let mut p = params();
+        p.erc20_allowlist.push(p.erc20_allowlist[0].to_lowercase());
+        assert!(p.validate().is_err());
+
+        let mut p = params();
+        p.erc20_allowlist = vec!["0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB".to_string()];
+        assert!(p.validate().is_err());
+
+        // minting the raw balance of a token with other decimals
+        let mut p = params();
+        p.cw20_decimals = 18;
+        assert!(p.validate().is_err());
+
+        let mut p = params();
+        p.cw20_decimals = 19;
+        p.erc20_decimals = 19;
+        assert!(p.validate().is_err());
+    }
+
+    #[test]
+    fn test_hash_ignores_address_case() {
+        let mut lowercase = params();
+        lowercase.erc20_allowlist[0] = lowercase.erc20_allowlist[0].to_lowercase();
+        assert_eq!(params().hash(), lowercase.hash());
+
+        let mut other = params();
+        other.balance_slot = 0;
+        assert_ne!(params().hash(), other.hash());
+    }
+}
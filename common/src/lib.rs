@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+pub mod signer;
+
 pub const REGULAR_MINT_CW20_LABEL: &str = "mint_cw20";
 pub const ZK_MINT_CW20_LABEL: &str = "zk_mint_cw20";
 
@@ -20,4 +22,31 @@ pub struct NeutronStrategyConfig {
 
     // coprocessor app id
     pub coprocessor_app_id: String,
+
+    // hex encoded hash of the parameters the circuit was built with, unset
+    // in the configs of deployments that predate it
+    #[serde(default)]
+    pub circuit_params_hash: Option<String>,
+
+    // label of the zk authorization verifying the current circuit
+    #[serde(default = "default_zk_authorization_label")]
+    pub zk_authorization_label: String,
+
+    // erc20 the circuit proves balances of
+    #[serde(default)]
+    pub erc20: String,
+
+    // ethereum account the strategist proves the erc20 balance of
+    #[serde(default)]
+    pub holder: String,
+}
+
+/// strategy configs of a multi-token manifest deployment, in manifest order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NeutronStrategyConfigs {
+    pub strategies: Vec<NeutronStrategyConfig>,
+}
+
+fn default_zk_authorization_label() -> String {
+    ZK_MINT_CW20_LABEL.to_string()
 }
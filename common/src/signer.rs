@@ -0,0 +1,158 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// path of a keystore encrypting the signer mnemonic
+pub const KEYSTORE_ENV: &str = "SIGNER_KEYSTORE";
+/// passphrase unlocking the `SIGNER_KEYSTORE`
+pub const PASSPHRASE_ENV: &str = "SIGNER_PASSPHRASE";
+/// path of a file holding the plaintext signer mnemonic
+pub const MNEMONIC_FILE_ENV: &str = "SIGNER_MNEMONIC_FILE";
+/// plaintext signer mnemonic, for development only
+pub const MNEMONIC_ENV: &str = "MNEMONIC";
+
+/// where the mnemonic of the neutron signing account is read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignerSource {
+    /// web3 secret storage keystore encrypting the mnemonic, unlocked with
+    /// the `SIGNER_PASSPHRASE`
+    Keystore(PathBuf),
+    /// plaintext mnemonic file, which must only be accessible by its owner
+    MnemonicFile(PathBuf),
+    /// the `MNEMONIC` env var
+    Env,
+}
+
+impl SignerSource {
+    /// `SIGNER_KEYSTORE`, else `SIGNER_MNEMONIC_FILE`, else the `MNEMONIC`
+    /// env var
+    pub fn from_env() -> Self {
+        if let Ok(path) = env::var(KEYSTORE_ENV) {
+            Self::Keystore(path.into())
+        } else if let Ok(path) = env::var(MNEMONIC_FILE_ENV) {
+            Self::MnemonicFile(path.into())
+        } else {
+            Self::Env
+        }
+    }
+
+    pub fn mnemonic(&self) -> anyhow::Result<String> {
+        match self {
+            Self::Keystore(path) => {
+                let passphrase = env::var(PASSPHRASE_ENV).map_err(|_| {
+                    anyhow::anyhow!("{PASSPHRASE_ENV} must be set to unlock the keystore {path:?}")
+                })?;
+
+                decrypt_mnemonic(path, &passphrase)
+            }
+            Self::MnemonicFile(path) => {
+                check_owner_only(path)?;
+
+                let mnemonic = fs::read_to_string(path)
+                    .map_err(|e| anyhow::anyhow!("failed to read the mnemonic {path:?}: {e}"))?;
+
+                Ok(mnemonic.trim().to_string())
+            }
+            Self::Env => {
+                let mnemonic = env::var(MNEMONIC_ENV).map_err(|_| {
+                    anyhow::anyhow!(
+                        "no signer configured, set {KEYSTORE_ENV}, {MNEMONIC_FILE_ENV} or {MNEMONIC_ENV}"
+                    )
+                })?;
+                eprintln!(
+                    "warning: signing with the plaintext {MNEMONIC_ENV} env var, use a keystore outside development"
+                );
+
+                Ok(mnemonic)
+            }
+        }
+    }
+}
+
+/// encrypts `mnemonic` into a new keystore at `path`
+pub fn encrypt_mnemonic(path: &Path, mnemonic: &str, passphrase: &str) -> anyhow::Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow::anyhow!("invalid keystore path {path:?}"))?;
+
+    eth_keystore::encrypt_key(
+        dir,
+        &mut rand::thread_rng(),
+        mnemonic.trim(),
+        passphrase,
+        Some(name),
+    )
+    .map_err(|e| anyhow::anyhow!("failed to encrypt the keystore {path:?}: {e}"))?;
+
+    Ok(())
+}
+
+fn decrypt_mnemonic(path: &Path, passphrase: &str) -> anyhow::Result<String> {
+    let mnemonic = eth_keystore::decrypt_key(path, passphrase)
+        .map_err(|e| anyhow::anyhow!("failed to decrypt the keystore {path:?}: {e}"))?;
+
+    String::from_utf8(mnemonic)
+        .map_err(|_| anyhow::anyhow!("keystore {path:?} does not hold a mnemonic"))
+}
+
+/// rejects files readable or writable by the group or other users
+#[cfg(unix)]
+fn check_owner_only(path: &Path) -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path)
+        .map_err(|e| anyhow::anyhow!("failed to read the mnemonic {path:?}: {e}"))?
+        .permissions()
+        .mode();
+    anyhow::ensure!(
+        mode & 0o077 == 0,
+        "mnemonic file {path:?} is accessible by other users (mode {:o}), restrict it with `chmod 600`",
+        mode & 0o777
+    );
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_owner_only(_path: &Path) -> anyhow::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+    #[test]
+    fn test_keystore_roundtrip() {
+        let path = env::temp_dir().join(format!("signer-keystore-{}", std::process::id()));
+
+        encrypt_mnemonic(&path, MNEMONIC, "passphrase").unwrap();
+        assert_eq!(decrypt_mnemonic(&path, "passphrase").unwrap(), MNEMONIC);
+        assert!(decrypt_mnemonic(&path, "wrong").is_err());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_mnemonic_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = env::temp_dir().join(format!("signer-mnemonic-{}", std::process::id()));
+        fs::write(&path, format!("{MNEMONIC}\n")).unwrap();
+        let source = SignerSource::MnemonicFile(path.clone());
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(source.mnemonic().is_err());
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(source.mnemonic().unwrap(), MNEMONIC);
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,47 +1,854 @@
 mod steps;
 
-use std::env;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
 
-use common::NeutronStrategyConfig;
-use valence_domain_clients::clients::neutron::NeutronClient;
+use clap::{Parser, Subcommand};
+use common::{
+    NeutronStrategyConfig, NeutronStrategyConfigs,
+    signer::{self, SignerSource},
+};
+use steps::{
+    Broadcaster, Checkpoint, CircuitHistory, CoprocessorApp, DeploymentReport, DryRun,
+    InstantiationOutputs, NeutronBroadcaster, NeutronInputs, PendingUpgrade, ProposalExport,
+    ProposalFormat, TxLog,
+};
+use storage_proof_core::params::CircuitParams;
+use valence_domain_clients::{
+    clients::neutron::NeutronClient, cosmos::grpc_client::GrpcSigningClient,
+};
+
+/// file name of the verification report, next to the output config
+const VERIFICATION_REPORT: &str = "deploy_verification.toml";
+
+/// file name of the replaced circuits record, next to the output config
+const CIRCUIT_HISTORY: &str = "circuit_history.toml";
+
+/// file name of the params the circuit is built with, next to the output
+/// config. git ignored, as every deployment rewrites it.
+const CIRCUIT_PARAMS: &str = "circuit_params.toml";
+
+/// file name of the deployment report of every run, next to the output config
+const DEPLOYMENT_REPORT: &str = "deploy_report.json";
+
+/// file name of the strategy configs of a manifest deployment, next to the
+/// output config
+const STRATEGY_CONFIGS: &str = "neutron_strategy_configs.toml";
+
+/// Valence program and circuit deployment
+#[derive(Debug, Parser)]
+struct Cli {
+    /// neutron inputs file
+    #[arg(
+        long,
+        global = true,
+        default_value = "deploy/src/inputs/neutron_inputs.toml"
+    )]
+    input: PathBuf,
+
+    /// strategy config file, written from the checkpoint after every command
+    #[arg(
+        long,
+        global = true,
+        default_value = "artifacts/neutron_strategy_config.toml"
+    )]
+    output: PathBuf,
+
+    /// env file selecting the deployer signer, see `common::signer`. defaults
+    /// to `.env`, if present
+    #[arg(long, global = true)]
+    env_file: Option<PathBuf>,
+
+    /// checkpoint file recording the completed steps
+    #[arg(
+        long,
+        global = true,
+        default_value = "artifacts/deploy_checkpoint.toml"
+    )]
+    checkpoint: PathBuf,
+
+    /// ignore the checkpoint and redeploy everything. the contract
+    /// addresses derive from the program name and version, so this fails
+    /// if contracts of the same program version already exist
+    #[arg(long, global = true)]
+    fresh: bool,
+
+    /// print the messages the deployment would broadcast, the circuit params
+    /// and a gas estimate, without signing or writing anything
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// deployer address a dry run plans for. dry runs sign nothing, and
+    /// only fall back to the address of the configured signer without it
+    #[arg(long, global = true, requires = "dry_run")]
+    sender: Option<String>,
+
+    /// write the contract executions to this governance proposal file
+    /// instead of broadcasting them, for contracts owned by a dao
+    #[arg(long, global = true, conflicts_with = "dry_run")]
+    proposal: Option<PathBuf>,
+
+    /// layout of the proposal file
+    #[arg(long, global = true, value_enum, default_value_t = ProposalFormat::DaoDao)]
+    proposal_format: ProposalFormat,
+
+    /// deposit of gov proposals, e.g. `1000000000untrn`
+    #[arg(long, global = true, requires = "proposal")]
+    proposal_deposit: Option<String>,
+
+    /// token manifest deploying a program per listed erc20, in place of the
+    /// erc20 and cw20 of the input file. the checkpoint and output config of
+    /// each token go to a directory named after its symbol, and the strategy
+    /// configs of every token are listed next to the output config
+    #[arg(long, global = true)]
+    manifest: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+impl Cli {
+    /// whether the contract executions are broadcast, rather than printed
+    /// or exported as a proposal
+    fn broadcasts(&self) -> bool {
+        !self.dry_run && self.proposal.is_none()
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// upload the configured wasm, then instantiate the authorization, processor
+    /// and cw20 contracts of the program version
+    Instantiate,
+    /// build and deploy the coprocessor app for the contracts in the output config
+    DeployApp,
+    /// create the regular and zk cw20 mint authorizations
+    SetupAuth,
+    /// hand the authorization contract and the admin of the program
+    /// contracts over to the configured owner, and verify the ownership
+    TransferOwnership,
+    /// redeploy the coprocessor app of a changed circuit and replace the zk
+    /// authorization with one verifying its new verifying key
+    UpgradeCircuit,
+    /// query every contract, check the deployment wiring and write a pass/fail
+    /// report next to the output config
+    Verify,
+    /// rewrite the output config from the checkpoint and the input node info
+    WriteArtifacts,
+    /// run every step in order, resuming from the checkpoint
+    All,
+    /// encrypt the mnemonic, prompted without echo, into a keystore at `path`,
+    /// unlocked with the `SIGNER_PASSPHRASE`
+    CreateKeystore { path: PathBuf },
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    dotenv::dotenv().ok();
-    let mnemonic = env::var("MNEMONIC")?;
+    let cli = Cli::parse();
+
+    match &cli.env_file {
+        Some(path) => {
+            dotenv::from_path(path)?;
+        }
+        None => {
+            dotenv::dotenv().ok();
+        }
+    }
+
+    // needs no inputs nor checkpoint
+    if let Command::CreateKeystore { path } = &cli.command {
+        return create_keystore(path);
+    }
+
     let current_dir = env::current_dir()?;
+    let neutron_inputs = steps::read_setup_inputs(&cli.input)?;
+
+    let Some(manifest) = &cli.manifest else {
+        deploy(
+            &cli,
+            current_dir,
+            &neutron_inputs,
+            &cli.checkpoint,
+            &cli.output,
+        )
+        .await?;
+        return Ok(());
+    };
+
+    let manifest = steps::read_manifest(manifest)?;
+    let mut strategies = vec![];
+    for token in &manifest.tokens {
+        println!("deploying token {}...", token.cw20.symbol);
+
+        let token_inputs = neutron_inputs.for_token(token)?;
+        let checkpoint = token_path(&cli.checkpoint, &token.id());
+        let output = token_path(&cli.output, &token.id());
+        if !cli.dry_run {
+            for path in [&checkpoint, &output] {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+            }
+        }
+
+        let cfg = deploy(
+            &cli,
+            current_dir.clone(),
+            &token_inputs,
+            &checkpoint,
+            &output,
+        )
+        .await?;
+        strategies.push(cfg);
+
+        // a later failing token keeps the configs of the deployed ones
+        write_strategy_configs(&cli, &strategies)?;
+    }
+
+    Ok(())
+}
+
+/// lists the strategy configs of the tokens deployed so far next to the
+/// output config
+fn write_strategy_configs(cli: &Cli, strategies: &[NeutronStrategyConfig]) -> anyhow::Result<()> {
+    let cfgs = NeutronStrategyConfigs {
+        strategies: strategies.to_vec(),
+    };
+    if cli.dry_run {
+        println!("[dry-run] strategy configs:\n{}", toml::to_string(&cfgs)?);
+        return Ok(());
+    }
+
+    let path = cli.output.with_file_name(STRATEGY_CONFIGS);
+    println!("writing the strategy configs of the deployed tokens to: {path:?}");
+
+    fs::write(path, toml::to_string(&cfgs)?)?;
+
+    Ok(())
+}
+
+/// `path` moved into the directory of the `token_id` artifacts
+fn token_path(path: &Path, token_id: &str) -> PathBuf {
+    let dir = path.parent().unwrap_or(Path::new("")).join(token_id);
+
+    match path.file_name() {
+        Some(name) => dir.join(name),
+        None => dir,
+    }
+}
+
+/// runs the command with the `checkpoint` file, appending the report of
+/// the run next to the `output` config, and returns the written config
+async fn deploy(
+    cli: &Cli,
+    current_dir: PathBuf,
+    neutron_inputs: &NeutronInputs,
+    checkpoint: &Path,
+    output: &Path,
+) -> anyhow::Result<NeutronStrategyConfig> {
+    let mut checkpoint = Checkpoint::load(checkpoint, cli.fresh)?;
+    if cli.dry_run {
+        checkpoint.detach();
+    }
+
+    let started_at = steps::unix_now()?;
+    let txs = TxLog::default();
+    let result = run(
+        cli,
+        current_dir,
+        neutron_inputs,
+        &mut checkpoint,
+        output,
+        &txs,
+    )
+    .await;
+
+    // dry runs broadcast nothing to audit
+    if !cli.dry_run {
+        let report = deployment_report(
+            cli,
+            neutron_inputs,
+            &checkpoint,
+            started_at,
+            &txs,
+            result.as_ref().err(),
+        )
+        .await
+        .and_then(|report| report.append(&output.with_file_name(DEPLOYMENT_REPORT)));
+        if let Err(e) = report {
+            println!("WARNING: failed to write the deployment report: {e}");
+        }
+    }
+
+    result
+}
+
+/// runs the command, then writes the `output` config from the checkpoint
+async fn run(
+    cli: &Cli,
+    current_dir: PathBuf,
+    neutron_inputs: &NeutronInputs,
+    checkpoint: &mut Checkpoint,
+    output: &Path,
+    txs: &TxLog,
+) -> anyhow::Result<NeutronStrategyConfig> {
+    match &cli.command {
+        Command::Instantiate => {
+            // new contracts invalidate every later step
+            checkpoint.reset();
+
+            let broadcaster = broadcaster(cli, neutron_inputs, txs).await?;
+            instantiate(broadcaster.as_ref(), neutron_inputs, checkpoint, cli.fresh).await?;
+        }
+        Command::DeployApp => {
+            resume_from_artifacts(checkpoint, output)?;
+            checkpoint.coprocessor_app = None;
+
+            deploy_app(current_dir, neutron_inputs, checkpoint, output, cli.dry_run)?;
+        }
+        Command::SetupAuth => {
+            resume_from_artifacts(checkpoint, output)?;
+
+            ensure_app_deployed(checkpoint)?;
+
+            let cfg = strategy_config(neutron_inputs, checkpoint)?;
+            let broadcaster = broadcaster(cli, neutron_inputs, txs).await?;
+            steps::setup_authorizations(
+                broadcaster.as_ref(),
+                &cfg,
+                &neutron_inputs.authorizations,
+                checkpoint,
+                cli.broadcasts(),
+            )
+            .await?;
+        }
+        Command::UpgradeCircuit => {
+            resume_from_artifacts(checkpoint, output)?;
+            ensure_app_deployed(checkpoint)?;
+
+            let history_path = output.with_file_name(CIRCUIT_HISTORY);
+            let mut history = CircuitHistory::load(&history_path)?;
+            let previous = strategy_config(neutron_inputs, checkpoint)?;
+
+            // the current app stays in the checkpoint until the rotation
+            // completes, so that a failed upgrade resumes instead of
+            // finding nothing to upgrade
+            let pending = match checkpoint.pending_upgrade.clone() {
+                Some(pending) => {
+                    println!("resuming the upgrade to coprocessor app {}", pending.app.id);
+                    pending
+                }
+                None => {
+                    // the circuit code changed even if its params did not
+                    let circuit_params = circuit_params(neutron_inputs, checkpoint)?;
+                    let app = new_app(current_dir, &circuit_params, output, cli.dry_run)?;
+                    anyhow::ensure!(
+                        cli.dry_run || steps::is_controller_id(&app.id),
+                        "invalid coprocessor app id {:?}",
+                        app.id
+                    );
+
+                    let pending = PendingUpgrade {
+                        app,
+                        zk_authorization_label: history
+                            .next_zk_authorization_label(&neutron_inputs.zk_authorization_label),
+                        zk_authorization: false,
+                    };
+                    checkpoint.pending_upgrade = Some(pending.clone());
+                    checkpoint.save()?;
+                    pending
+                }
+            };
+
+            let next = NeutronStrategyConfig {
+                coprocessor_app_id: pending.app.id.clone(),
+                circuit_params_hash: Some(pending.app.circuit_params_hash.clone()),
+                zk_authorization_label: pending.zk_authorization_label.clone(),
+                ..previous.clone()
+            };
+
+            let broadcaster = broadcaster(cli, neutron_inputs, txs).await?;
+            let deployer = broadcaster.sender().await?;
+            let record = steps::upgrade_circuit(
+                broadcaster.as_ref(),
+                &previous,
+                &next,
+                steps::authorization_mode(&neutron_inputs.authorizations.zk, &deployer),
+                checkpoint,
+                cli.broadcasts(),
+            )
+            .await?;
+
+            if let Some(record) = record {
+                history.circuits.push(record);
+                if !cli.dry_run {
+                    history.save(&history_path)?;
+                }
+            }
+        }
+        Command::TransferOwnership => {
+            resume_from_artifacts(checkpoint, output)?;
+
+            let broadcaster = broadcaster(cli, neutron_inputs, txs).await?;
+            transfer_ownership(
+                broadcaster.as_ref(),
+                neutron_inputs,
+                checkpoint,
+                cli.broadcasts(),
+            )
+            .await?;
+        }
+        Command::Verify => {
+            resume_from_artifacts(checkpoint, output)?;
+
+            let cfg = strategy_config(neutron_inputs, checkpoint)?;
+            let broadcaster = broadcaster(cli, neutron_inputs, txs).await?;
+            verify(
+                broadcaster.as_ref(),
+                &cfg,
+                neutron_inputs,
+                output,
+                cli.dry_run,
+            )
+            .await?;
+        }
+        Command::WriteArtifacts => resume_from_artifacts(checkpoint, output)?,
+        Command::All => {
+            let broadcaster = broadcaster(cli, neutron_inputs, txs).await?;
+
+            instantiate(broadcaster.as_ref(), neutron_inputs, checkpoint, cli.fresh).await?;
+            deploy_app(current_dir, neutron_inputs, checkpoint, output, cli.dry_run)?;
+
+            let cfg = strategy_config(neutron_inputs, checkpoint)?;
+            println!("neutron strategy config: {:?}", cfg);
+
+            steps::setup_authorizations(
+                broadcaster.as_ref(),
+                &cfg,
+                &neutron_inputs.authorizations,
+                checkpoint,
+                cli.broadcasts(),
+            )
+            .await?;
 
-    let neutron_inputs = steps::read_setup_inputs(current_dir.clone())?;
+            // the executions of dry runs and proposals are not on chain yet
+            if cli.broadcasts() {
+                verify(
+                    broadcaster.as_ref(),
+                    &cfg,
+                    neutron_inputs,
+                    output,
+                    cli.dry_run,
+                )
+                .await?;
+            }
 
-    let neutron_client = NeutronClient::new(
+            // last, the deployer can no longer create authorizations afterwards
+            transfer_ownership(
+                broadcaster.as_ref(),
+                neutron_inputs,
+                checkpoint,
+                cli.broadcasts(),
+            )
+            .await?;
+        }
+        Command::CreateKeystore { .. } => unreachable!("handled before loading the inputs"),
+    }
+
+    // the artifacts always reflect the checkpoint, whichever steps ran
+    let cfg = strategy_config(neutron_inputs, checkpoint)?;
+    if cli.dry_run {
+        println!("[dry-run] strategy config:\n{}", toml::to_string(&cfg)?);
+    } else {
+        steps::write_setup_artifacts(output, &cfg)?;
+    }
+
+    Ok(cfg)
+}
+
+fn create_keystore(path: &Path) -> anyhow::Result<()> {
+    anyhow::ensure!(!path.exists(), "keystore {path:?} already exists");
+    let passphrase = env::var(signer::PASSPHRASE_ENV)
+        .map_err(|_| anyhow::anyhow!("{} must be set", signer::PASSPHRASE_ENV))?;
+
+    // read without echo, so the mnemonic stays off the terminal
+    let mnemonic = rpassword::prompt_password("enter the mnemonic: ")?;
+
+    signer::encrypt_mnemonic(path, &mnemonic, &passphrase)?;
+    println!(
+        "keystore written to {path:?}, set {}={} to sign with it",
+        signer::KEYSTORE_ENV,
+        path.display()
+    );
+
+    Ok(())
+}
+
+/// audit record of the run started at `started_at`, from the checkpoint
+/// and the txs broadcast by the run
+async fn deployment_report(
+    cli: &Cli,
+    neutron_inputs: &NeutronInputs,
+    checkpoint: &Checkpoint,
+    started_at: u64,
+    txs: &TxLog,
+    error: Option<&anyhow::Error>,
+) -> anyhow::Result<DeploymentReport> {
+    let app = checkpoint.coprocessor_app.as_ref();
+
+    // the report of a failed run still matters more than its vk
+    let vk_hash = match app {
+        Some(app) if steps::is_controller_id(&app.id) => match steps::program_vk(&app.id).await {
+            Ok(vk) => Some(hex::encode(vk.as_slice())),
+            Err(e) => {
+                println!(
+                    "WARNING: failed to fetch the verifying key of app {}: {e}",
+                    app.id
+                );
+                None
+            }
+        },
+        _ => None,
+    };
+
+    Ok(DeploymentReport {
+        command: format!("{:?}", cli.command),
+        started_at,
+        finished_at: steps::unix_now()?,
+        error: error.map(|e| format!("{e:#}")),
+        inputs: cli.input.clone(),
+        inputs_sha256: steps::file_sha256(&cli.input)?,
+        manifest: cli.manifest.clone(),
+        manifest_sha256: cli
+            .manifest
+            .as_deref()
+            .map(steps::file_sha256)
+            .transpose()?,
+        code_ids: checkpoint.code_ids.clone(),
+        contracts: checkpoint.instantiation.clone(),
+        coprocessor_app_id: app.map(|app| app.id.clone()),
+        circuit_params_hash: app.map(|app| app.circuit_params_hash.clone()),
+        vk_hash,
+        zk_authorization_label: checkpoint.zk_authorization.then(|| {
+            checkpoint
+                .zk_authorization_label
+                .clone()
+                .unwrap_or_else(|| neutron_inputs.zk_authorization_label.clone())
+        }),
+        transactions: txs.lock().unwrap().clone(),
+    })
+}
+
+async fn neutron_client(neutron_inputs: &NeutronInputs) -> anyhow::Result<NeutronClient> {
+    let mnemonic = SignerSource::from_env().mnemonic()?;
+
+    NeutronClient::new(
         &neutron_inputs.grpc_url,
         &neutron_inputs.grpc_port,
         &mnemonic,
         &neutron_inputs.chain_id,
     )
-    .await?;
-
-    let instantiation_outputs =
-        steps::instantiate_contracts(&neutron_client, neutron_inputs.code_ids).await?;
-
-    let coprocessor_app_id =
-        steps::deploy_coprocessor_app(current_dir.clone(), &instantiation_outputs.cw20)?;
-
-    let neutron_strategy_config = NeutronStrategyConfig {
-        grpc_url: neutron_inputs.grpc_url,
-        grpc_port: neutron_inputs.grpc_port,
-        chain_id: neutron_inputs.chain_id,
-        authorizations: instantiation_outputs.authorizations,
-        processor: instantiation_outputs.processor,
-        cw20: instantiation_outputs.cw20,
-        coprocessor_app_id,
+    .await
+}
+
+/// address of the configured signer
+async fn signer_address(neutron_inputs: &NeutronInputs) -> anyhow::Result<String> {
+    let client = neutron_client(neutron_inputs).await?;
+
+    Ok(client.get_signing_client().await?.address.to_string())
+}
+
+/// signs and broadcasts with the neutron client, recording the included
+/// txs in `txs`, only prints the messages on dry runs, or exports the
+/// contract executions as a proposal
+async fn broadcaster(
+    cli: &Cli,
+    neutron_inputs: &NeutronInputs,
+    txs: &TxLog,
+) -> anyhow::Result<Box<dyn Broadcaster>> {
+    if cli.dry_run {
+        let sender = match &cli.sender {
+            Some(sender) => sender.clone(),
+            None => signer_address(neutron_inputs).await.map_err(|e| {
+                anyhow::anyhow!("{e}, pass the deployer address with --sender to dry run")
+            })?,
+        };
+        let grpc_endpoint = format!("{}:{}", neutron_inputs.grpc_url, neutron_inputs.grpc_port);
+
+        return Ok(Box::new(DryRun::connect(grpc_endpoint, sender).await?));
+    }
+
+    let neutron_client =
+        NeutronBroadcaster::new(neutron_client(neutron_inputs).await?, txs.clone());
+
+    Ok(match &cli.proposal {
+        Some(path) => {
+            // the dao only executes on contracts handed over to it
+            anyhow::ensure!(
+                !matches!(cli.command, Command::Instantiate | Command::All),
+                "proposals execute as the owner, which holds the contracts only after \
+                 transfer-ownership. deploy without --proposal first"
+            );
+
+            let program = &neutron_inputs.program;
+            let title = format!(
+                "{} version {}: {:?}",
+                program.name, program.version, cli.command
+            );
+            let owner = neutron_inputs
+                .ownership
+                .as_ref()
+                .map(|ownership| ownership.owner.clone());
+
+            Box::new(ProposalExport::new(
+                Box::new(neutron_client),
+                path.clone(),
+                cli.proposal_format,
+                owner,
+                cli.proposal_deposit.clone(),
+                title,
+            )?)
+        }
+        None => Box::new(neutron_client),
+    })
+}
+
+/// uploads the contract code and instantiates the contracts, unless the
+/// checkpoint already records them. `fresh` deployments refuse to reuse
+/// contracts living at the predicted addresses.
+async fn instantiate(
+    broadcaster: &dyn Broadcaster,
+    neutron_inputs: &NeutronInputs,
+    checkpoint: &mut Checkpoint,
+    fresh: bool,
+) -> anyhow::Result<()> {
+    if let Some(outputs) = &checkpoint.instantiation {
+        println!("contracts already instantiated, skipping: {outputs:?}");
+        return Ok(());
+    }
+
+    let code_ids = match &checkpoint.code_ids {
+        Some(code_ids) => {
+            println!("contract code already stored, skipping: {code_ids:?}");
+            code_ids.clone()
+        }
+        None => {
+            let code_ids = steps::upload_contracts(broadcaster, neutron_inputs, checkpoint).await?;
+
+            checkpoint.code_ids = Some(code_ids.clone());
+            checkpoint.save()?;
+            code_ids
+        }
     };
 
-    println!("neutron strategy config: {:?}", neutron_strategy_config);
+    let outputs =
+        steps::instantiate_contracts(broadcaster, neutron_inputs, &code_ids, !fresh).await?;
+
+    checkpoint.instantiation = Some(outputs);
+    checkpoint.save()
+}
 
-    steps::setup_authorizations(&neutron_client, &neutron_strategy_config).await?;
+/// deploys the coprocessor app minting on the instantiated cw20, unless
+/// the checkpoint already records an app built with the same params
+fn deploy_app(
+    current_dir: PathBuf,
+    neutron_inputs: &NeutronInputs,
+    checkpoint: &mut Checkpoint,
+    output: &Path,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let circuit_params = circuit_params(neutron_inputs, checkpoint)?;
+    let circuit_params_hash = hex::encode(circuit_params.hash());
 
-    steps::write_setup_artifacts(current_dir, neutron_strategy_config)?;
+    if let Some(app) = &checkpoint.coprocessor_app {
+        if app.circuit_params_hash == circuit_params_hash {
+            println!("coprocessor app {} already deployed, skipping", app.id);
+            return Ok(());
+        }
+    }
+
+    checkpoint.coprocessor_app = Some(new_app(current_dir, &circuit_params, output, dry_run)?);
+    checkpoint.save()
+}
+
+/// params of the circuit minting on the instantiated cw20
+fn circuit_params(
+    neutron_inputs: &NeutronInputs,
+    checkpoint: &Checkpoint,
+) -> anyhow::Result<CircuitParams> {
+    let instantiation = checkpoint
+        .instantiation
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("no instantiated contracts, run instantiate first"))?;
+
+    Ok(CircuitParams {
+        network: neutron_inputs.network.clone(),
+        cw20_addr: instantiation.cw20.clone(),
+        erc20_allowlist: vec![neutron_inputs.erc20.address.clone()],
+        erc20_decimals: neutron_inputs.erc20.decimals,
+        cw20_decimals: neutron_inputs.cw20_decimals(),
+        balance_slot: neutron_inputs.erc20.balance_slot,
+    })
+}
+
+/// deploys a coprocessor app built with `circuit_params`
+fn new_app(
+    current_dir: PathBuf,
+    circuit_params: &CircuitParams,
+    output: &Path,
+    dry_run: bool,
+) -> anyhow::Result<CoprocessorApp> {
+    let id = steps::deploy_coprocessor_app(
+        current_dir,
+        circuit_params,
+        &output.with_file_name(CIRCUIT_PARAMS),
+        dry_run,
+    )?;
+
+    Ok(CoprocessorApp {
+        id,
+        circuit_params_hash: hex::encode(circuit_params.hash()),
+    })
+}
+
+/// checks the deployment wiring, writing the report next to the output
+/// config. fails if any check failed.
+async fn verify(
+    broadcaster: &dyn Broadcaster,
+    cfg: &NeutronStrategyConfig,
+    neutron_inputs: &NeutronInputs,
+    output: &Path,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let policies = &neutron_inputs.authorizations;
+    let deployer = broadcaster.sender().await?;
+    let report = steps::verify_deployment(
+        broadcaster,
+        cfg,
+        &neutron_inputs.verification_gateway,
+        &steps::authorization_mode(&policies.regular, &deployer),
+        &steps::authorization_mode(&policies.zk, &deployer),
+    )
+    .await;
+
+    if !dry_run {
+        report.write(&output.with_file_name(VERIFICATION_REPORT))?;
+    }
+
+    anyhow::ensure!(report.passed, "deployment verification failed");
+
+    Ok(())
+}
+
+/// hands the program over to the configured owner, unless the checkpoint
+/// records it as done, and verifies the resulting ownership
+async fn transfer_ownership(
+    broadcaster: &dyn Broadcaster,
+    neutron_inputs: &NeutronInputs,
+    checkpoint: &mut Checkpoint,
+    broadcasts: bool,
+) -> anyhow::Result<()> {
+    let Some(ownership) = &neutron_inputs.ownership else {
+        println!("no final owner configured, the deployer keeps ownership");
+        return Ok(());
+    };
+
+    let contracts = checkpoint
+        .instantiation
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("no instantiated contracts, run instantiate first"))?;
+
+    if checkpoint.ownership_transferred {
+        println!("ownership already transferred, skipping");
+    } else {
+        steps::transfer_ownership(broadcaster, &contracts, ownership).await?;
+
+        // an exported proposal is not executed yet, the next run skips
+        // whatever it finds on chain
+        checkpoint.ownership_transferred = broadcasts;
+        checkpoint.save()?;
+    }
+
+    // nothing to verify until dry runs and proposals are executed
+    if broadcasts {
+        steps::verify_ownership(broadcaster, &contracts, ownership).await?;
+    }
+
+    Ok(())
+}
+
+/// never create a zk authorization for a failed app deployment
+fn ensure_app_deployed(checkpoint: &Checkpoint) -> anyhow::Result<()> {
+    let app = checkpoint
+        .coprocessor_app
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("no coprocessor app deployed, run deploy-app first"))?;
+    anyhow::ensure!(
+        steps::is_controller_id(&app.id),
+        "invalid coprocessor app id {:?}, run deploy-app again",
+        app.id
+    );
 
     Ok(())
 }
+
+/// seeds an empty checkpoint with a deployment made before checkpoints
+/// were recorded, so its steps can be rerun individually
+fn resume_from_artifacts(checkpoint: &mut Checkpoint, output: &Path) -> anyhow::Result<()> {
+    if checkpoint.instantiation.is_some() || !output.exists() {
+        return Ok(());
+    }
+
+    let cfg = steps::read_setup_artifacts(output)?;
+    println!("resuming the deployment recorded in {output:?}");
+
+    checkpoint.instantiation = Some(InstantiationOutputs {
+        cw20: cfg.cw20,
+        processor: cfg.processor,
+        authorizations: cfg.authorizations,
+    });
+    if !cfg.coprocessor_app_id.is_empty() {
+        // an unknown params hash matches no params, so deploy-app redeploys
+        checkpoint.coprocessor_app = Some(CoprocessorApp {
+            id: cfg.coprocessor_app_id,
+            circuit_params_hash: cfg.circuit_params_hash.unwrap_or_default(),
+        });
+    }
+    checkpoint.zk_authorization_label = Some(cfg.zk_authorization_label);
+
+    checkpoint.save()
+}
+
+/// strategy config of the deployment recorded in the checkpoint. steps
+/// that did not run yet are left empty.
+fn strategy_config(
+    neutron_inputs: &NeutronInputs,
+    checkpoint: &Checkpoint,
+) -> anyhow::Result<NeutronStrategyConfig> {
+    let instantiation = checkpoint
+        .instantiation
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("no instantiated contracts, run instantiate first"))?;
+    let app = checkpoint.coprocessor_app.clone();
+
+    Ok(NeutronStrategyConfig {
+        grpc_url: neutron_inputs.grpc_url.clone(),
+        grpc_port: neutron_inputs.grpc_port.clone(),
+        chain_id: neutron_inputs.chain_id.clone(),
+        authorizations: instantiation.authorizations,
+        processor: instantiation.processor,
+        cw20: instantiation.cw20,
+        coprocessor_app_id: app.as_ref().map(|a| a.id.clone()).unwrap_or_default(),
+        circuit_params_hash: app
+            .map(|a| a.circuit_params_hash)
+            .filter(|hash| !hash.is_empty()),
+        erc20: neutron_inputs.erc20.address.clone(),
+        holder: neutron_inputs.erc20.holder.clone().unwrap_or_default(),
+        zk_authorization_label: checkpoint
+            .zk_authorization_label
+            .clone()
+            .unwrap_or_else(|| neutron_inputs.zk_authorization_label.clone()),
+    })
+}
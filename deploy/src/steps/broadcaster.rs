@@ -0,0 +1,506 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use async_trait::async_trait;
+use cosmrs::{
+    AccountId, Any,
+    cosmwasm::{MsgStoreCode, MsgUpdateAdmin},
+    proto::{
+        cosmos::base::abci::v1beta1::TxResponse,
+        cosmwasm::wasm::v1::{
+            ContractInfo, MsgInstantiateContract2, QueryCodeRequest, QueryContractInfoRequest,
+            QuerySmartContractStateRequest, query_client::QueryClient,
+        },
+    },
+    tx::Msg,
+};
+use cosmwasm_std::Binary;
+use serde_json::{Value, json};
+use sp1_sdk::{HashableKey, SP1VerifyingKey};
+use tonic::transport::Channel;
+use valence_domain_clients::{
+    clients::{coprocessor::CoprocessorClient, neutron::NeutronClient},
+    coprocessor::base_client::CoprocessorBaseClient,
+    cosmos::{base_client::BaseClient, grpc_client::GrpcSigningClient, wasm_client::WasmClient},
+};
+
+use crate::steps::{TxLog, TxRecord, deploy_coprocessor_app::DRY_RUN_APP_ID};
+
+/// side effects of the deploy steps. `NeutronBroadcaster` signs and
+/// broadcasts every message; `DryRun` prints them instead, without a signer.
+#[async_trait]
+pub trait Broadcaster: Send + Sync {
+    /// address the messages are sent from
+    async fn sender(&self) -> anyhow::Result<String>;
+
+    async fn predict_instantiate2_addr(
+        &self,
+        code_id: u64,
+        salt: &str,
+        creator: &str,
+    ) -> anyhow::Result<String>;
+
+    /// instantiates `code_id` at its predictable address, returning it
+    async fn instantiate2(
+        &self,
+        code_id: u64,
+        label: &str,
+        msg: Value,
+        admin: Option<String>,
+        salt: &str,
+    ) -> anyhow::Result<String>;
+
+    /// wasm info, such as the code id and admin, of the contract at
+    /// `addr`, if there is one
+    async fn contract_info(&self, addr: &str) -> anyhow::Result<Option<ContractInfo>>;
+
+    async fn query(&self, contract: &str, msg: Value) -> anyhow::Result<Value>;
+
+    /// executes `msg` on `contract` and waits for its inclusion
+    async fn execute(&self, contract: &str, msg: Value) -> anyhow::Result<()>;
+
+    /// sets the wasm admin of `contract`, able to migrate it
+    async fn update_admin(&self, contract: &str, new_admin: &str) -> anyhow::Result<()>;
+
+    /// uploads `wasm`, returning its code id
+    async fn store_code(&self, wasm: Vec<u8>) -> anyhow::Result<u64>;
+
+    /// sha256 checksum of the wasm stored as `code_id`
+    async fn code_checksum(&self, code_id: u64) -> anyhow::Result<Vec<u8>>;
+
+    /// bytes32 of the sp1 verifying key of the coprocessor app
+    async fn program_vk(&self, app_id: &str) -> anyhow::Result<Binary>;
+}
+
+/// signs and broadcasts with the neutron client, recording every included
+/// transaction in the tx log
+pub struct NeutronBroadcaster {
+    client: NeutronClient,
+    txs: TxLog,
+}
+
+impl NeutronBroadcaster {
+    pub fn new(client: NeutronClient, txs: TxLog) -> Self {
+        Self { client, txs }
+    }
+
+    fn record(&self, description: String, response: &TxResponse) {
+        println!(
+            "tx {} included at height {}",
+            response.txhash, response.height
+        );
+
+        self.txs
+            .lock()
+            .unwrap()
+            .push(TxRecord::new(description, response));
+    }
+
+    /// signs and broadcasts a message the wasm client has no wrapper for,
+    /// waiting for its inclusion
+    async fn broadcast_any(&self, description: String, msg: Any) -> anyhow::Result<TxResponse> {
+        let client = &self.client;
+        let signing_client = client.get_signing_client().await?;
+
+        let simulation = client.simulate_tx(msg.clone()).await?;
+        let fee = client.get_tx_fee(simulation)?;
+        let raw_tx = signing_client.create_tx(msg, fee, None).await?;
+        let rx = client.broadcast_tx(raw_tx).await?;
+
+        let response = client.poll_for_tx(&rx.hash).await?;
+        self.record(description, &response);
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl Broadcaster for NeutronBroadcaster {
+    async fn sender(&self) -> anyhow::Result<String> {
+        Ok(self.client.get_signing_client().await?.address.to_string())
+    }
+
+    async fn predict_instantiate2_addr(
+        &self,
+        code_id: u64,
+        salt: &str,
+        creator: &str,
+    ) -> anyhow::Result<String> {
+        Ok(WasmClient::predict_instantiate2_addr(
+            &self.client,
+            code_id,
+            salt.to_string(),
+            creator.to_string(),
+        )
+        .await?
+        .address)
+    }
+
+    async fn instantiate2(
+        &self,
+        code_id: u64,
+        label: &str,
+        msg: Value,
+        admin: Option<String>,
+        salt: &str,
+    ) -> anyhow::Result<String> {
+        let sender = self.sender().await?;
+        let predicted = self
+            .predict_instantiate2_addr(code_id, salt, &sender)
+            .await?;
+
+        // built here rather than with the wasm client to keep the tx response
+        let msg = Any::from_msg(&MsgInstantiateContract2 {
+            sender,
+            admin: admin.unwrap_or_default(),
+            code_id,
+            label: label.to_string(),
+            msg: serde_json::to_vec(&msg)?,
+            funds: vec![],
+            salt: salt.as_bytes().to_vec(),
+            fix_msg: false,
+        })
+        .map_err(|e| anyhow::anyhow!("failed to encode the instantiation of {label}: {e}"))?;
+
+        let response = self
+            .broadcast_any(format!("instantiate2 {label} (code id {code_id})"), msg)
+            .await?;
+
+        let addr = response
+            .events
+            .iter()
+            .filter(|event| event.r#type == "instantiate")
+            .flat_map(|event| &event.attributes)
+            .find(|attribute| attribute.key == "_contract_address")
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "no contract address in the events of tx {}",
+                    response.txhash
+                )
+            })?
+            .value
+            .clone();
+        anyhow::ensure!(
+            addr == predicted,
+            "{label} instantiated at {addr}, expected the predicted {predicted}"
+        );
+
+        Ok(addr)
+    }
+
+    async fn contract_info(&self, addr: &str) -> anyhow::Result<Option<ContractInfo>> {
+        query_contract_info(self.client.get_grpc_channel().await?, addr).await
+    }
+
+    async fn execute(&self, contract: &str, msg: Value) -> anyhow::Result<()> {
+        let rx = self
+            .client
+            .execute_wasm(contract, msg, vec![], None)
+            .await?;
+        let response = self.client.poll_for_tx(&rx.hash).await?;
+        self.record(format!("execute on {contract}"), &response);
+
+        Ok(())
+    }
+
+    async fn query(&self, contract: &str, msg: Value) -> anyhow::Result<Value> {
+        self.client.query_contract_state(contract, msg).await
+    }
+
+    async fn update_admin(&self, contract: &str, new_admin: &str) -> anyhow::Result<()> {
+        let sender = self.client.get_signing_client().await?.address;
+
+        let msg = MsgUpdateAdmin {
+            sender,
+            new_admin: parse_account(new_admin)?,
+            contract: parse_account(contract)?,
+        }
+        .to_any()
+        .map_err(|e| anyhow::anyhow!("failed to encode the admin update: {e}"))?;
+
+        self.broadcast_any(format!("update admin of {contract}"), msg)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn store_code(&self, wasm: Vec<u8>) -> anyhow::Result<u64> {
+        let sender = self.client.get_signing_client().await?.address;
+
+        let msg = MsgStoreCode {
+            sender,
+            wasm_byte_code: wasm,
+            instantiate_permission: None,
+        }
+        .to_any()
+        .map_err(|e| anyhow::anyhow!("failed to encode the code upload: {e}"))?;
+
+        let response = self.broadcast_any("store code".to_string(), msg).await?;
+
+        response
+            .events
+            .iter()
+            .filter(|event| event.r#type == "store_code")
+            .flat_map(|event| &event.attributes)
+            .find(|attribute| attribute.key == "code_id")
+            .ok_or_else(|| anyhow::anyhow!("no code id in the events of tx {}", response.txhash))?
+            .value
+            .parse()
+            .map_err(|e| anyhow::anyhow!("invalid code id in tx {}: {e}", response.txhash))
+    }
+
+    async fn code_checksum(&self, code_id: u64) -> anyhow::Result<Vec<u8>> {
+        query_code_checksum(self.client.get_grpc_channel().await?, code_id).await
+    }
+
+    async fn program_vk(&self, app_id: &str) -> anyhow::Result<Binary> {
+        program_vk(app_id).await
+    }
+}
+
+/// bytes32 of the sp1 verifying key of the coprocessor app
+pub async fn program_vk(app_id: &str) -> anyhow::Result<Binary> {
+    let program_vk = CoprocessorClient::default().get_vk(app_id).await?;
+    let sp1_program_vk: SP1VerifyingKey = bincode::deserialize(&program_vk)?;
+
+    Ok(Binary::from(sp1_program_vk.bytes32().as_bytes()))
+}
+
+/// wasm info of the contract at `addr`, if there is one
+async fn query_contract_info(channel: Channel, addr: &str) -> anyhow::Result<Option<ContractInfo>> {
+    let request = QueryContractInfoRequest {
+        address: addr.to_string(),
+    };
+
+    match QueryClient::new(channel).contract_info(request).await {
+        Ok(response) => Ok(response.into_inner().contract_info),
+        // wasmd reports unknown contracts as a "no such contract" error
+        Err(status)
+            if status.code() == tonic::Code::NotFound
+                || status.message().contains("no such contract") =>
+        {
+            Ok(None)
+        }
+        Err(status) => Err(anyhow::anyhow!(
+            "failed to query the contract info of {addr}: {status}"
+        )),
+    }
+}
+
+/// sha256 checksum of the wasm stored as `code_id`
+async fn query_code_checksum(channel: Channel, code_id: u64) -> anyhow::Result<Vec<u8>> {
+    let code_info = QueryClient::new(channel)
+        .code(QueryCodeRequest { code_id })
+        .await
+        .map_err(|status| anyhow::anyhow!("failed to query code {code_id}: {status}"))?
+        .into_inner()
+        .code_info
+        .ok_or_else(|| anyhow::anyhow!("no code stored with id {code_id}"))?;
+
+    Ok(code_info.data_hash)
+}
+
+/// address `creator` instantiates the code of `checksum` at with `salt`,
+/// the way wasmd derives instantiate2 addresses without a fixed msg
+fn instantiate2_addr(checksum: &[u8], creator: &str, salt: &str) -> anyhow::Result<String> {
+    let creator = parse_account(creator)?;
+    let canonical =
+        cosmwasm_std::instantiate2_address(checksum, &creator.to_bytes().into(), salt.as_bytes())
+            .map_err(|e| anyhow::anyhow!("failed to derive the instantiate2 address: {e}"))?;
+
+    AccountId::new(creator.prefix(), canonical.as_slice())
+        .map(|addr| addr.to_string())
+        .map_err(|e| anyhow::anyhow!("failed to encode the instantiate2 address: {e}"))
+}
+
+fn parse_account(addr: &str) -> anyhow::Result<AccountId> {
+    addr.parse()
+        .map_err(|e| anyhow::anyhow!("invalid address {addr}: {e}"))
+}
+
+/// rough gas used by an instantiation, before its message size
+const INSTANTIATE_BASE_GAS: u64 = 300_000;
+/// rough gas used by an execution, before its message size
+const EXECUTE_BASE_GAS: u64 = 200_000;
+/// rough gas used by a wasm admin update
+const UPDATE_ADMIN_GAS: u64 = 100_000;
+/// rough gas per byte of uploaded wasm
+const GAS_PER_WASM_BYTE: u64 = 15;
+/// rough gas per byte of json message stored or parsed by the contract
+const GAS_PER_MSG_BYTE: u64 = 30;
+
+/// prints every message instead of broadcasting it, on behalf of the
+/// given sender. needs no signer: queries, such as predicted addresses,
+/// go to the node over a plain grpc channel. the gas figures are rough
+/// size based guesses, not simulations, which would need a signer.
+pub struct DryRun {
+    channel: Channel,
+    sender: String,
+    gas: AtomicU64,
+}
+
+impl DryRun {
+    /// connects to the grpc endpoint of the node, e.g. `http://host:9090`
+    pub async fn connect(grpc_endpoint: String, sender: String) -> anyhow::Result<Self> {
+        let channel = Channel::from_shared(grpc_endpoint.clone())
+            .map_err(|e| anyhow::anyhow!("invalid grpc endpoint {grpc_endpoint}: {e}"))?
+            .connect()
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to connect to {grpc_endpoint}: {e}"))?;
+        parse_account(&sender)?;
+
+        println!(
+            "[dry-run] planning as {sender}. gas figures are rough guesses from the message \
+             sizes, not simulations"
+        );
+
+        Ok(Self {
+            channel,
+            sender,
+            gas: AtomicU64::new(0),
+        })
+    }
+
+    fn print_msg(&self, title: &str, msg: &Value, base_gas: u64) {
+        let pretty = serde_json::to_string_pretty(msg).unwrap_or_default();
+
+        println!("[dry-run] {title}:\n{pretty}");
+        self.add_gas(base_gas + GAS_PER_MSG_BYTE * pretty.len() as u64);
+    }
+
+    fn add_gas(&self, gas: u64) {
+        let total = self.gas.fetch_add(gas, Ordering::Relaxed) + gas;
+
+        println!("[dry-run] rough gas guess, not simulated: {gas} (total {total})");
+    }
+}
+
+#[async_trait]
+impl Broadcaster for DryRun {
+    async fn sender(&self) -> anyhow::Result<String> {
+        Ok(self.sender.clone())
+    }
+
+    async fn predict_instantiate2_addr(
+        &self,
+        code_id: u64,
+        salt: &str,
+        creator: &str,
+    ) -> anyhow::Result<String> {
+        let checksum = query_code_checksum(self.channel.clone(), code_id).await?;
+
+        instantiate2_addr(&checksum, creator, salt)
+    }
+
+    async fn instantiate2(
+        &self,
+        code_id: u64,
+        label: &str,
+        msg: Value,
+        admin: Option<String>,
+        salt: &str,
+    ) -> anyhow::Result<String> {
+        let addr = self
+            .predict_instantiate2_addr(code_id, salt, &self.sender)
+            .await?;
+
+        self.print_msg(
+            &format!(
+                "instantiate2 {label} (code id {code_id}, admin {admin:?}, salt {salt}) at {addr}"
+            ),
+            &msg,
+            INSTANTIATE_BASE_GAS,
+        );
+
+        Ok(addr)
+    }
+
+    async fn contract_info(&self, addr: &str) -> anyhow::Result<Option<ContractInfo>> {
+        query_contract_info(self.channel.clone(), addr).await
+    }
+
+    async fn query(&self, contract: &str, msg: Value) -> anyhow::Result<Value> {
+        let request = QuerySmartContractStateRequest {
+            address: contract.to_string(),
+            query_data: serde_json::to_vec(&msg)?,
+        };
+        let response = QueryClient::new(self.channel.clone())
+            .smart_contract_state(request)
+            .await
+            .map_err(|status| anyhow::anyhow!("failed to query {contract}: {status}"))?;
+
+        Ok(serde_json::from_slice(&response.into_inner().data)?)
+    }
+
+    async fn execute(&self, contract: &str, msg: Value) -> anyhow::Result<()> {
+        self.print_msg(&format!("execute on {contract}"), &msg, EXECUTE_BASE_GAS);
+
+        Ok(())
+    }
+
+    async fn update_admin(&self, contract: &str, new_admin: &str) -> anyhow::Result<()> {
+        self.print_msg(
+            &format!("update admin of {contract}"),
+            &json!({ "new_admin": new_admin }),
+            UPDATE_ADMIN_GAS,
+        );
+
+        Ok(())
+    }
+
+    async fn store_code(&self, wasm: Vec<u8>) -> anyhow::Result<u64> {
+        println!("[dry-run] store code of {} bytes", wasm.len());
+        self.add_gas(GAS_PER_WASM_BYTE * wasm.len() as u64);
+
+        // the instantiate2 addresses derive from the stored code, which
+        // is only known once it is on chain
+        anyhow::bail!("[dry-run] cannot plan past a code upload, set the code ids of stored code")
+    }
+
+    async fn code_checksum(&self, code_id: u64) -> anyhow::Result<Vec<u8>> {
+        query_code_checksum(self.channel.clone(), code_id).await
+    }
+
+    async fn program_vk(&self, app_id: &str) -> anyhow::Result<Binary> {
+        // a dry run deploys no app, so there is no verifying key to print.
+        // the vk of a deployed app must be fetched like on a real run.
+        if app_id == DRY_RUN_APP_ID {
+            println!("[dry-run] the app is not deployed, the zk authorization vk is all zeroes");
+            return Ok(Binary::from([0u8; 32].as_slice()));
+        }
+
+        program_vk(app_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CREATOR: &str = "neutron1m6w8n0hluq7avn40hj0n6jnj8ejhykfrwfnnjh";
+
+    #[test]
+    fn test_instantiate2_addr() {
+        let checksum = [7u8; 32];
+
+        let addr = instantiate2_addr(&checksum, CREATOR, "authorization").unwrap();
+        let (hrp, data) = bech32::decode(&addr).unwrap();
+        assert_eq!(hrp.as_str(), "neutron");
+        assert_eq!(data.len(), 32);
+
+        // deterministic, and distinct per salt, code and creator
+        assert_eq!(
+            addr,
+            instantiate2_addr(&checksum, CREATOR, "authorization").unwrap()
+        );
+        assert_ne!(
+            addr,
+            instantiate2_addr(&checksum, CREATOR, "processor").unwrap()
+        );
+        assert_ne!(
+            addr,
+            instantiate2_addr(&[8u8; 32], CREATOR, "authorization").unwrap()
+        );
+
+        assert!(instantiate2_addr(&checksum, "neutron1invalid", "authorization").is_err());
+    }
+}
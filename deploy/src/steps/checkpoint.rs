@@ -0,0 +1,98 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::steps::{CodeIds, InstantiationOutputs};
+
+/// coprocessor app deployed by the `deploy_coprocessor_app` step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoprocessorApp {
+    pub id: String,
+    /// hex encoded hash of the circuit params the app was built with
+    pub circuit_params_hash: String,
+}
+
+/// circuit upgrade deployed by `upgrade-circuit`, replacing the current
+/// coprocessor app once its zk authorization is rotated in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingUpgrade {
+    pub app: CoprocessorApp,
+    pub zk_authorization_label: String,
+    /// whether the zk authorization of the new app was created
+    #[serde(default)]
+    pub zk_authorization: bool,
+}
+
+/// outputs of the completed deployment steps. saved after every step so
+/// that a failed deployment resumes where it stopped instead of
+/// instantiating a fresh set of contracts.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// file the checkpoint is saved to. `None` keeps it in memory only
+    #[serde(skip)]
+    path: Option<PathBuf>,
+
+    /// code ids of the wasm stored so far, by contract, so that a failed
+    /// upload does not store the previous contracts again
+    #[serde(default)]
+    pub stored_code: BTreeMap<String, u64>,
+    /// code ids of the uploaded, or configured, contract code
+    pub code_ids: Option<CodeIds>,
+    pub instantiation: Option<InstantiationOutputs>,
+    pub coprocessor_app: Option<CoprocessorApp>,
+    #[serde(default)]
+    pub regular_authorization: bool,
+    #[serde(default)]
+    pub zk_authorization: bool,
+    /// label of the zk authorization, once rotated by a circuit upgrade
+    #[serde(default)]
+    pub zk_authorization_label: Option<String>,
+    #[serde(default)]
+    pub ownership_transferred: bool,
+    /// upgrade whose zk authorization rotation did not complete yet
+    #[serde(default)]
+    pub pending_upgrade: Option<PendingUpgrade>,
+}
+
+impl Checkpoint {
+    /// loads the checkpoint at `path`. a missing file, or `fresh`, starts
+    /// an empty checkpoint.
+    pub fn load(path: &Path, fresh: bool) -> anyhow::Result<Self> {
+        let mut checkpoint = if fresh || !path.exists() {
+            Self::default()
+        } else {
+            let contents = fs::read_to_string(path)?;
+            toml::from_str(&contents)
+                .map_err(|e| anyhow::anyhow!("corrupted deploy checkpoint {path:?}: {e}"))?
+        };
+
+        checkpoint.path = Some(path.to_path_buf());
+
+        Ok(checkpoint)
+    }
+
+    /// clears every completed step
+    pub fn reset(&mut self) {
+        *self = Self {
+            path: self.path.clone(),
+            ..Default::default()
+        };
+    }
+
+    /// stops saving the checkpoint, e.g. for dry runs
+    pub fn detach(&mut self) {
+        self.path = None;
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(path) = &self.path {
+            fs::write(path, toml::to_string(self)?)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,42 +1,159 @@
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use serde_json::Value;
+use storage_proof_core::params::CircuitParams;
 
-const CIRCUIT_CONSTS_PATH: &str = "circuits/storage_proof/core/src/consts.rs";
 const CONTROLLER_PATH: &str = "./circuits/storage_proof/controller";
 const CIRCUIT_WORKSPACE_ID: &str = "storage-proof-circuit";
+/// coprocessor service the circuit is deployed to
+const COPROCESSOR_SOCKET: &str = "https://service.coprocessor.valence.zone";
+
+/// env var the storage-proof-core build script reads the params file from
+const CIRCUIT_PARAMS_ENV: &str = "CIRCUIT_PARAMS";
+
+/// app id reported by a dry run, which deploys nothing
+pub const DRY_RUN_APP_ID: &str = "<coprocessor app id>";
+
+/// failed coprocessor app deployment, carrying the raw service response
+#[derive(Debug, thiserror::Error)]
+pub enum CoprocessorDeployError {
+    #[error("coprocessor deploy response is not a json object: {response}")]
+    MalformedResponse { response: Value },
+    #[error("coprocessor deploy response has no controller id: {response}")]
+    MissingController { response: Value },
+    #[error("invalid controller id {id:?}, expected 32 hex encoded bytes: {response}")]
+    InvalidControllerId { id: String, response: Value },
+}
 
-pub fn deploy_coprocessor_app(cd: PathBuf, cw20_addr: &str) -> anyhow::Result<String> {
+/// writes the `circuit_params` to the untracked `params_path`, then builds
+/// and deploys the circuit with them
+pub fn deploy_coprocessor_app(
+    cd: PathBuf,
+    circuit_params: &CircuitParams,
+    params_path: &Path,
+    dry_run: bool,
+) -> anyhow::Result<String> {
     println!("deploying coprocessor app...");
 
-    // this can also be done with env passing.
-    // not obvious which one is cleaner yet.
-    let generated_addr_path = cd.join(CIRCUIT_CONSTS_PATH);
-    let generated_addr_content = format!(
-        "// DO NOT EDIT. This file is automatically generated by deploy_coprocessor_app.rs.\npub const CW20_ADDR: &str = \"{}\";",
-        cw20_addr
+    let params_toml = toml::to_string(circuit_params)?;
+
+    if dry_run {
+        println!(
+            "[dry-run] circuit params with hash {}:\n{params_toml}",
+            hex::encode(circuit_params.hash())
+        );
+        return Ok(DRY_RUN_APP_ID.to_string());
+    }
+
+    // the circuit parameters are compiled into storage-proof-core by its
+    // build script, which validates them again before the circuit is built
+    circuit_params.validate()?;
+
+    let params_path = cd.join(params_path);
+    fs::write(&params_path, params_toml)?;
+    println!(
+        "wrote circuit params with hash {} to {:?}",
+        hex::encode(circuit_params.hash()),
+        params_path
+    );
+
+    // the params reach the build script through the environment of the
+    // cargo-valence child alone
+    let deployment = Command::new("cargo-valence")
+        .current_dir(&cd)
+        .env(CIRCUIT_PARAMS_ENV, &params_path)
+        .args(["--socket", COPROCESSOR_SOCKET, "deploy", "circuit"])
+        .args(["--controller", CONTROLLER_PATH])
+        .args(["--circuit", CIRCUIT_WORKSPACE_ID])
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| anyhow::anyhow!("failed to run cargo-valence, is it installed? {e}"))?;
+    anyhow::ensure!(
+        deployment.status.success(),
+        "cargo-valence deploy circuit failed: {}",
+        deployment.status
     );
-    fs::write(&generated_addr_path, generated_addr_content)?;
-    println!("embedded CW20 address into {:?}", generated_addr_path);
-
-    let cargo_valence_app = cargo_valence::App::default();
-
-    let circuit_deployment_response =
-        cargo_valence_app.deploy_circuit(Some(CONTROLLER_PATH), CIRCUIT_WORKSPACE_ID)?;
-
-    let controller_id = match circuit_deployment_response.as_object() {
-        Some(obj) => match obj.get("controller").and_then(|v| v.as_str()) {
-            Some(id) => id.to_owned(),
-            None => {
-                println!("failed to get controller from response object: {obj:?}");
-                "deployment_error".to_string()
-            }
-        },
-        None => {
-            println!("failed to deploy the circuit: {circuit_deployment_response:?}");
-            "deployment_error".to_string()
-        }
-    };
+
+    let circuit_deployment_response: Value =
+        serde_json::from_slice(&deployment.stdout).map_err(|e| {
+            anyhow::anyhow!(
+                "cargo-valence deploy circuit printed no json response: {e}: {}",
+                String::from_utf8_lossy(&deployment.stdout)
+            )
+        })?;
+
+    let controller_id = parse_deploy_response(circuit_deployment_response)?;
 
     println!("controller_id: {controller_id}");
 
     Ok(controller_id)
 }
+
+/// extracts the controller id from the coprocessor deploy response
+pub fn parse_deploy_response(response: Value) -> Result<String, CoprocessorDeployError> {
+    let Some(obj) = response.as_object() else {
+        return Err(CoprocessorDeployError::MalformedResponse { response });
+    };
+
+    let Some(id) = obj.get("controller").and_then(|v| v.as_str()) else {
+        return Err(CoprocessorDeployError::MissingController { response });
+    };
+
+    if !is_controller_id(id) {
+        return Err(CoprocessorDeployError::InvalidControllerId {
+            id: id.to_string(),
+            response,
+        });
+    }
+
+    Ok(id.to_string())
+}
+
+/// whether `id` is a 32 byte hex encoded controller id
+pub fn is_controller_id(id: &str) -> bool {
+    matches!(hex::decode(id), Ok(bytes) if bytes.len() == 32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const CONTROLLER_ID: &str = "7a376137d6f6dfc03c750681f9e346c593aad55b3ad0c43cabf323ba97165389";
+
+    #[test]
+    fn test_parse_deploy_response() {
+        let id = parse_deploy_response(json!({ "controller": CONTROLLER_ID })).unwrap();
+        assert_eq!(id, CONTROLLER_ID);
+    }
+
+    #[test]
+    fn test_parse_deploy_response_errors() {
+        assert!(matches!(
+            parse_deploy_response(json!("internal server error")),
+            Err(CoprocessorDeployError::MalformedResponse { .. })
+        ));
+        assert!(matches!(
+            parse_deploy_response(json!({ "error": "build failed" })),
+            Err(CoprocessorDeployError::MissingController { .. })
+        ));
+
+        for id in [
+            "deployment_error",
+            &CONTROLLER_ID[2..],
+            &format!("0x{CONTROLLER_ID}"),
+        ] {
+            let err = parse_deploy_response(json!({ "controller": id })).unwrap_err();
+            assert!(matches!(
+                err,
+                CoprocessorDeployError::InvalidControllerId { .. }
+            ));
+            // the raw response is kept for debugging
+            assert!(err.to_string().contains(id));
+        }
+    }
+}
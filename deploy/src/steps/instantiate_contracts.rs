@@ -1,130 +1,179 @@
-use std::time::SystemTime;
+use cw20::{Logo, MinterResponse};
+use cw20_base::msg::InstantiateMarketingInfo;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
 
-use cw20::MinterResponse;
-use valence_domain_clients::{
-    clients::neutron::NeutronClient,
-    cosmos::{base_client::BaseClient, grpc_client::GrpcSigningClient, wasm_client::WasmClient},
+use crate::steps::{
+    Broadcaster,
+    read_input::{CodeIds, NeutronInputs, ProgramInputs},
 };
 
-use crate::steps::read_input::CodeIds;
-
-const VALENCE_NEUTRON_VERIFICATION_GATEWAY: &str =
-    "neutron1l3fgzcqse0xw84hdpytg7vcp04kcdm95wes2zd6ap8kpujmv9cwsv45wwk";
+const AUTHORIZATION_LABEL: &str = "authorization";
+const PROCESSOR_LABEL: &str = "processor";
+const CW20_LABEL: &str = "mirror_cw20";
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InstantiationOutputs {
     pub cw20: String,
     pub processor: String,
     pub authorizations: String,
 }
 
+/// instantiate2 salt of the `label` contract of a program. the same
+/// program name, version and deployer always yield the same addresses.
+pub fn instantiate2_salt(program: &ProgramInputs, label: &str) -> String {
+    let preimage = format!("{}/{}/{label}", program.name, program.version);
+
+    hex::encode(Sha256::digest(preimage.as_bytes()))
+}
+
+/// instantiates the program contracts at their predicted addresses. with
+/// `reuse`, contracts already living there are reused and reconciled with
+/// the inputs; without it, an occupied address is an error, as the same
+/// program version can't be deployed twice.
 pub async fn instantiate_contracts(
-    neutron_client: &NeutronClient,
-    code_ids: CodeIds,
+    broadcaster: &dyn Broadcaster,
+    neutron_inputs: &NeutronInputs,
+    code_ids: &CodeIds,
+    reuse: bool,
 ) -> anyhow::Result<InstantiationOutputs> {
-    println!("instantiating contracts...");
+    let program = &neutron_inputs.program;
 
-    let my_address = neutron_client
-        .get_signing_client()
-        .await?
-        .address
-        .to_string();
+    println!(
+        "instantiating contracts of {} version {}...",
+        program.name, program.version
+    );
+
+    let my_address = broadcaster.sender().await?;
 
     println!("runner address: {my_address}");
 
-    let now = SystemTime::now();
-    let salt_raw = now
-        .duration_since(SystemTime::UNIX_EPOCH)?
-        .as_secs()
-        .to_string();
-    let salt = hex::encode(salt_raw.as_bytes());
+    let authorization_salt = instantiate2_salt(program, AUTHORIZATION_LABEL);
+    let processor_salt = instantiate2_salt(program, PROCESSOR_LABEL);
+    let cw20_salt = instantiate2_salt(program, CW20_LABEL);
 
-    let predicted_processor_address = neutron_client
-        .predict_instantiate2_addr(code_ids.processor, salt.clone(), my_address.clone())
-        .await?
-        .address;
+    let authorization_address = broadcaster
+        .predict_instantiate2_addr(code_ids.authorizations, &authorization_salt, &my_address)
+        .await?;
+    let processor_address = broadcaster
+        .predict_instantiate2_addr(code_ids.processor, &processor_salt, &my_address)
+        .await?;
+    let cw20_addr = broadcaster
+        .predict_instantiate2_addr(code_ids.cw20, &cw20_salt, &my_address)
+        .await?;
+
+    println!("predicted authorization addr: {authorization_address}");
+    println!("predicted processor addr: {processor_address}");
+    println!("predicted cw20 addr: {cw20_addr}");
 
-    println!("predicted processor addr: {predicted_processor_address}");
+    if !reuse {
+        for addr in [&authorization_address, &processor_address, &cw20_addr] {
+            anyhow::ensure!(
+                broadcaster.contract_info(addr).await?.is_none(),
+                "{addr} is already taken: the salts derive from the program name and version, \
+                 so a fresh deployment of {} version {} can't get new contracts. bump the \
+                 program version, or drop --fresh to reuse them",
+                program.name,
+                program.version
+            );
+        }
+    }
 
     // Owner will initially be the deploy address and eventually will be transferred to the owned address
     let authorization_instantiate_msg = valence_authorization_utils::msg::InstantiateMsg {
         owner: my_address.to_string(),
         sub_owners: vec![],
-        processor: predicted_processor_address.clone(),
+        processor: processor_address.clone(),
     };
 
     println!("instantiating authorization address...");
-    let authorization_address = neutron_client
-        .instantiate2(
-            code_ids.authorizations,
-            "authorization".to_string(),
-            authorization_instantiate_msg,
-            Some(my_address.to_string()),
-            salt.clone(),
-        )
-        .await?;
-    println!("Authorization instantiated: {authorization_address}");
+    let authorization_created = instantiate2_or_reuse(
+        broadcaster,
+        code_ids.authorizations,
+        AUTHORIZATION_LABEL,
+        json!(authorization_instantiate_msg),
+        &my_address,
+        &authorization_salt,
+        &authorization_address,
+    )
+    .await?;
 
     let processor_instantiate_msg = valence_processor_utils::msg::InstantiateMsg {
         authorization_contract: authorization_address.clone(),
         polytone_contracts: None,
     };
 
-    let processor_address = neutron_client
-        .instantiate2(
-            code_ids.processor,
-            "processor".to_string(),
-            processor_instantiate_msg,
-            Some(my_address.to_string()),
-            salt.clone(),
-        )
-        .await?;
-    println!("Processor instantiated: {processor_address}");
-
-    // Set the verification gateway address on the authorization contract
-    let set_verification_gateway_msg =
-        valence_authorization_utils::msg::ExecuteMsg::PermissionedAction(
-            valence_authorization_utils::msg::PermissionedMsg::SetVerificationGateway {
-                verification_gateway: VALENCE_NEUTRON_VERIFICATION_GATEWAY.to_string(),
-            },
-        );
+    instantiate2_or_reuse(
+        broadcaster,
+        code_ids.processor,
+        PROCESSOR_LABEL,
+        json!(processor_instantiate_msg),
+        &my_address,
+        &processor_salt,
+        &processor_address,
+    )
+    .await?;
 
-    let set_verification_gateway_rx = neutron_client
-        .execute_wasm(
-            &authorization_address,
-            set_verification_gateway_msg,
-            vec![],
-            None,
-        )
-        .await?;
+    // a reused authorization contract may point at another gateway
+    let gateway = match authorization_created {
+        true => None,
+        false => verification_gateway(broadcaster, &authorization_address).await?,
+    };
+    if gateway.as_deref() == Some(neutron_inputs.verification_gateway.as_str()) {
+        println!(
+            "verification gateway already set to {}",
+            neutron_inputs.verification_gateway
+        );
+    } else {
+        let set_verification_gateway_msg =
+            valence_authorization_utils::msg::ExecuteMsg::PermissionedAction(
+                valence_authorization_utils::msg::PermissionedMsg::SetVerificationGateway {
+                    verification_gateway: neutron_inputs.verification_gateway.clone(),
+                },
+            );
 
-    neutron_client
-        .poll_for_tx(&set_verification_gateway_rx.hash)
-        .await?;
+        broadcaster
+            .execute(&authorization_address, json!(set_verification_gateway_msg))
+            .await?;
 
-    println!("Set verification gateway address to {VALENCE_NEUTRON_VERIFICATION_GATEWAY}");
+        println!(
+            "Set verification gateway address to {} (was {gateway:?})",
+            neutron_inputs.verification_gateway
+        );
+    }
 
+    let cw20 = &neutron_inputs.cw20;
     let cw20_init_msg = cw20_base::msg::InstantiateMsg {
-        name: "test_playground".to_string(),
-        symbol: "CWBASETEST".to_string(),
-        decimals: 18,
+        name: cw20.name.clone(),
+        symbol: cw20.symbol.clone(),
+        decimals: neutron_inputs.cw20_decimals(),
         initial_balances: vec![],
         mint: Some(MinterResponse {
             minter: processor_address.to_string(),
-            cap: None,
+            cap: cw20.cap,
         }),
-        marketing: None,
+        marketing: cw20
+            .marketing
+            .clone()
+            .map(|marketing| InstantiateMarketingInfo {
+                project: marketing.project,
+                description: marketing.description,
+                marketing: marketing.marketing,
+                logo: marketing.logo_url.map(Logo::Url),
+            }),
     };
 
-    let cw20_addr = neutron_client
-        .instantiate(
-            code_ids.cw20,
-            "mirror_cw20".to_string(),
-            cw20_init_msg,
-            Some(my_address),
-        )
-        .await?;
-
-    println!("CW20 Instantiated: {cw20_addr}");
+    instantiate2_or_reuse(
+        broadcaster,
+        code_ids.cw20,
+        CW20_LABEL,
+        json!(cw20_init_msg),
+        cw20.admin.as_deref().unwrap_or(&my_address),
+        &cw20_salt,
+        &cw20_addr,
+    )
+    .await?;
 
     let outputs = InstantiationOutputs {
         cw20: cw20_addr,
@@ -134,3 +183,81 @@ pub async fn instantiate_contracts(
 
     Ok(outputs)
 }
+
+/// verification gateway of the `authorizations` contract, `None` while unset
+pub async fn verification_gateway(
+    broadcaster: &dyn Broadcaster,
+    authorizations: &str,
+) -> anyhow::Result<Option<String>> {
+    let query = valence_authorization_utils::msg::QueryMsg::VerificationGateway {};
+
+    match broadcaster.query(authorizations, json!(query)).await {
+        Ok(gateway) => Ok(gateway.as_str().filter(|g| !g.is_empty()).map(String::from)),
+        // the contract fails to load a gateway that was never set
+        Err(e) if e.to_string().contains("not found") => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// instantiates `code_id` at its `predicted` address, unless a contract
+/// of the same code already lives there. returns whether it was created.
+async fn instantiate2_or_reuse(
+    broadcaster: &dyn Broadcaster,
+    code_id: u64,
+    label: &str,
+    msg: Value,
+    admin: &str,
+    salt: &str,
+    predicted: &str,
+) -> anyhow::Result<bool> {
+    let existing_code_id = broadcaster
+        .contract_info(predicted)
+        .await?
+        .map(|info| info.code_id);
+
+    match existing_code_id {
+        Some(existing) if existing == code_id => {
+            println!("{label} already instantiated at {predicted}, reusing it");
+            Ok(false)
+        }
+        Some(existing) => anyhow::bail!(
+            "{predicted} is already taken by a contract of code {existing}, expected code {code_id} for {label}"
+        ),
+        None => {
+            let addr = broadcaster
+                .instantiate2(code_id, label, msg, Some(admin.to_string()), salt)
+                .await?;
+            anyhow::ensure!(
+                addr == predicted,
+                "{label} was instantiated at {addr}, predicted {predicted}"
+            );
+
+            println!("{label} instantiated: {addr}");
+            Ok(true)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instantiate2_salt() {
+        let program = ProgramInputs {
+            name: "x-vault".to_string(),
+            version: "1".to_string(),
+        };
+
+        let salt = instantiate2_salt(&program, PROCESSOR_LABEL);
+        assert_eq!(salt, instantiate2_salt(&program, PROCESSOR_LABEL));
+        assert_eq!(hex::decode(&salt).unwrap().len(), 32);
+        assert_ne!(salt, instantiate2_salt(&program, CW20_LABEL));
+
+        let next = ProgramInputs {
+            version: "2".to_string(),
+            ..program.clone()
+        };
+        assert_ne!(salt, instantiate2_salt(&next, PROCESSOR_LABEL));
+    }
+}
@@ -1,11 +1,27 @@
+mod broadcaster;
+mod checkpoint;
 mod deploy_coprocessor_app;
 mod instantiate_contracts;
+mod proposal;
 mod read_input;
+mod report;
 mod setup_authorizations;
+mod transfer_ownership;
+mod upgrade_circuit;
+mod upload_contracts;
+mod verify;
 mod write_output;
 
-pub use deploy_coprocessor_app::deploy_coprocessor_app;
-pub use instantiate_contracts::instantiate_contracts;
+pub use broadcaster::{Broadcaster, DryRun, NeutronBroadcaster, program_vk};
+pub use checkpoint::{Checkpoint, CoprocessorApp, PendingUpgrade};
+pub use deploy_coprocessor_app::{deploy_coprocessor_app, is_controller_id};
+pub use instantiate_contracts::{InstantiationOutputs, instantiate_contracts};
+pub use proposal::{ProposalExport, ProposalFormat};
 pub use read_input::*;
-pub use setup_authorizations::setup_authorizations;
-pub use write_output::write_setup_artifacts;
+pub use report::{DeploymentReport, TxLog, TxRecord, file_sha256, unix_now};
+pub use setup_authorizations::{authorization_mode, setup_authorizations};
+pub use transfer_ownership::{transfer_ownership, verify_ownership};
+pub use upgrade_circuit::{CircuitHistory, upgrade_circuit};
+pub use upload_contracts::upload_contracts;
+pub use verify::verify_deployment;
+pub use write_output::{read_setup_artifacts, write_setup_artifacts};
@@ -0,0 +1,332 @@
+use std::{fs, path::PathBuf, sync::Mutex};
+
+use async_trait::async_trait;
+use cosmrs::proto::cosmwasm::wasm::v1::ContractInfo;
+use cosmwasm_std::{Binary, CosmosMsg, Empty, WasmMsg, to_json_binary};
+use serde_json::{Value, json};
+
+use crate::steps::Broadcaster;
+
+/// payload layout of an exported proposal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ProposalFormat {
+    /// `propose` message of a DAO DAO single choice proposal module
+    DaoDao,
+    /// proposal file of `tx gov submit-proposal`
+    Gov,
+}
+
+/// message of an exported proposal
+#[derive(Debug, Clone)]
+enum ProposalMsg {
+    Execute { contract: String, msg: Value },
+    UpdateAdmin { contract: String, new_admin: String },
+}
+
+/// collects the contract executions and admin updates into a governance
+/// proposal instead of broadcasting them, for contracts owned by a dao.
+/// queries go to the wrapped broadcaster, while uploads and instantiations
+/// are refused: the dao only owns contracts after their handover.
+pub struct ProposalExport {
+    inner: Box<dyn Broadcaster>,
+    path: PathBuf,
+    format: ProposalFormat,
+    /// executing account, the dao or gov module
+    sender: Option<String>,
+    /// deposit of gov proposals, e.g. `1000000000untrn`
+    deposit: Option<String>,
+    title: String,
+    msgs: Mutex<Vec<ProposalMsg>>,
+}
+
+impl ProposalExport {
+    pub fn new(
+        inner: Box<dyn Broadcaster>,
+        path: PathBuf,
+        format: ProposalFormat,
+        sender: Option<String>,
+        deposit: Option<String>,
+        title: String,
+    ) -> anyhow::Result<Self> {
+        if format == ProposalFormat::Gov {
+            anyhow::ensure!(
+                sender.is_some(),
+                "gov proposals execute as the owner, set it in the [ownership] inputs"
+            );
+            let deposit = deposit.as_deref().unwrap_or_default();
+            anyhow::ensure!(
+                is_coin(deposit),
+                "gov proposals need a deposit such as 1000000000untrn, got {deposit:?}"
+            );
+        }
+
+        Ok(Self {
+            inner,
+            path,
+            format,
+            sender,
+            deposit,
+            title,
+            msgs: Mutex::new(vec![]),
+        })
+    }
+
+    /// adds `msg` to the proposal, rewriting the proposal file so that it
+    /// holds every message collected so far
+    fn push(&self, msg: ProposalMsg) -> anyhow::Result<()> {
+        let proposal = {
+            let mut msgs = self.msgs.lock().unwrap();
+            msgs.push(msg);
+
+            proposal_payload(
+                self.format,
+                self.sender.as_deref(),
+                self.deposit.as_deref(),
+                &self.title,
+                &msgs,
+            )?
+        };
+
+        fs::write(&self.path, serde_json::to_string_pretty(&proposal)?)?;
+
+        Ok(())
+    }
+}
+
+/// whether `coin` is an amount followed by a denom, e.g. `100untrn`
+fn is_coin(coin: &str) -> bool {
+    let denom = coin.trim_start_matches(|c: char| c.is_ascii_digit());
+
+    denom.len() < coin.len()
+        && denom.starts_with(|c: char| c.is_ascii_alphabetic())
+        && denom
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/:._-".contains(c))
+}
+
+/// proposal executing every message in order
+fn proposal_payload(
+    format: ProposalFormat,
+    sender: Option<&str>,
+    deposit: Option<&str>,
+    title: &str,
+    msgs: &[ProposalMsg],
+) -> anyhow::Result<Value> {
+    let description = format!("executes {} valence program messages", msgs.len());
+
+    Ok(match format {
+        ProposalFormat::DaoDao => {
+            let msgs = msgs
+                .iter()
+                .map(|msg| {
+                    Ok(CosmosMsg::<Empty>::Wasm(match msg {
+                        ProposalMsg::Execute { contract, msg } => WasmMsg::Execute {
+                            contract_addr: contract.clone(),
+                            msg: to_json_binary(msg)?,
+                            funds: vec![],
+                        },
+                        ProposalMsg::UpdateAdmin {
+                            contract,
+                            new_admin,
+                        } => WasmMsg::UpdateAdmin {
+                            contract_addr: contract.clone(),
+                            admin: new_admin.clone(),
+                        },
+                    }))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            json!({
+                "propose": {
+                    "title": title,
+                    "description": description,
+                    "msgs": msgs,
+                }
+            })
+        }
+        ProposalFormat::Gov => {
+            let messages: Vec<Value> = msgs
+                .iter()
+                .map(|msg| match msg {
+                    ProposalMsg::Execute { contract, msg } => json!({
+                        "@type": "/cosmwasm.wasm.v1.MsgExecuteContract",
+                        "sender": sender,
+                        "contract": contract,
+                        "msg": msg,
+                        "funds": [],
+                    }),
+                    ProposalMsg::UpdateAdmin {
+                        contract,
+                        new_admin,
+                    } => json!({
+                        "@type": "/cosmwasm.wasm.v1.MsgUpdateAdmin",
+                        "sender": sender,
+                        "new_admin": new_admin,
+                        "contract": contract,
+                    }),
+                })
+                .collect();
+
+            json!({
+                "messages": messages,
+                "metadata": "",
+                "deposit": deposit,
+                "title": title,
+                "summary": description,
+            })
+        }
+    })
+}
+
+#[async_trait]
+impl Broadcaster for ProposalExport {
+    async fn sender(&self) -> anyhow::Result<String> {
+        self.inner.sender().await
+    }
+
+    async fn predict_instantiate2_addr(
+        &self,
+        code_id: u64,
+        salt: &str,
+        creator: &str,
+    ) -> anyhow::Result<String> {
+        self.inner
+            .predict_instantiate2_addr(code_id, salt, creator)
+            .await
+    }
+
+    async fn instantiate2(
+        &self,
+        _code_id: u64,
+        label: &str,
+        _msg: Value,
+        _admin: Option<String>,
+        _salt: &str,
+    ) -> anyhow::Result<String> {
+        anyhow::bail!(
+            "proposals do not instantiate contracts, instantiate {label} without --proposal"
+        )
+    }
+
+    async fn contract_info(&self, addr: &str) -> anyhow::Result<Option<ContractInfo>> {
+        self.inner.contract_info(addr).await
+    }
+
+    async fn query(&self, contract: &str, msg: Value) -> anyhow::Result<Value> {
+        self.inner.query(contract, msg).await
+    }
+
+    async fn execute(&self, contract: &str, msg: Value) -> anyhow::Result<()> {
+        self.push(ProposalMsg::Execute {
+            contract: contract.to_string(),
+            msg,
+        })?;
+        println!(
+            "added a message on {contract} to the proposal {:?}",
+            self.path
+        );
+
+        Ok(())
+    }
+
+    async fn update_admin(&self, contract: &str, new_admin: &str) -> anyhow::Result<()> {
+        self.push(ProposalMsg::UpdateAdmin {
+            contract: contract.to_string(),
+            new_admin: new_admin.to_string(),
+        })?;
+        println!(
+            "added the admin update of {contract} to the proposal {:?}",
+            self.path
+        );
+
+        Ok(())
+    }
+
+    async fn store_code(&self, _wasm: Vec<u8>) -> anyhow::Result<u64> {
+        anyhow::bail!("proposals do not store contract code, upload it without --proposal")
+    }
+
+    async fn code_checksum(&self, code_id: u64) -> anyhow::Result<Vec<u8>> {
+        self.inner.code_checksum(code_id).await
+    }
+
+    async fn program_vk(&self, app_id: &str) -> anyhow::Result<Binary> {
+        self.inner.program_vk(app_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn execute_msg() -> Value {
+        json!({
+            "permissioned_action": {
+                "set_verification_gateway": { "verification_gateway": "neutron1gateway" }
+            }
+        })
+    }
+
+    fn msgs() -> Vec<ProposalMsg> {
+        vec![
+            ProposalMsg::Execute {
+                contract: "neutron1authorization".to_string(),
+                msg: execute_msg(),
+            },
+            ProposalMsg::UpdateAdmin {
+                contract: "neutron1processor".to_string(),
+                new_admin: "neutron1dao".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_dao_dao_proposal() {
+        let proposal =
+            proposal_payload(ProposalFormat::DaoDao, None, None, "title", &msgs()).unwrap();
+
+        let execute = &proposal["propose"]["msgs"][0]["wasm"]["execute"];
+        assert_eq!(execute["contract_addr"], "neutron1authorization");
+        assert_eq!(
+            execute["msg"],
+            json!(to_json_binary(&execute_msg()).unwrap())
+        );
+
+        let update_admin = &proposal["propose"]["msgs"][1]["wasm"]["update_admin"];
+        assert_eq!(update_admin["contract_addr"], "neutron1processor");
+        assert_eq!(update_admin["admin"], "neutron1dao");
+    }
+
+    #[test]
+    fn test_gov_proposal() {
+        let proposal = proposal_payload(
+            ProposalFormat::Gov,
+            Some("neutron1dao"),
+            Some("1000untrn"),
+            "title",
+            &msgs(),
+        )
+        .unwrap();
+
+        assert_eq!(proposal["deposit"], "1000untrn");
+
+        let message = &proposal["messages"][0];
+        assert_eq!(message["@type"], "/cosmwasm.wasm.v1.MsgExecuteContract");
+        assert_eq!(message["sender"], "neutron1dao");
+        assert_eq!(message["msg"], execute_msg());
+
+        let message = &proposal["messages"][1];
+        assert_eq!(message["@type"], "/cosmwasm.wasm.v1.MsgUpdateAdmin");
+        assert_eq!(message["new_admin"], "neutron1dao");
+    }
+
+    #[test]
+    fn test_is_coin() {
+        assert!(is_coin("1000untrn"));
+        assert!(is_coin("1ibc/ABC"));
+
+        for coin in ["", "untrn", "1000", "1000 untrn", "1000/untrn"] {
+            assert!(!is_coin(coin), "{coin}");
+        }
+    }
+}
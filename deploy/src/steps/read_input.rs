@@ -1,31 +1,620 @@
-use common::INPUTS_DIR;
-use serde::Deserialize;
-use std::{fs, path::PathBuf};
+use common::ZK_MINT_CW20_LABEL;
+use cosmwasm_std::Uint128;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use storage_proof_core::{
+    network::{NetworkCodeIds, NetworkProfile},
+    params::MAX_CW20_DECIMALS,
+};
 
-#[derive(Debug, Clone, Deserialize)]
+/// deploy inputs, with the network profile defaults applied
+#[derive(Debug, Clone)]
 pub struct NeutronInputs {
+    /// name of the selected network profile
+    pub network: String,
     pub grpc_url: String,
     pub grpc_port: String,
     pub chain_id: String,
-    pub code_ids: CodeIds,
+    pub verification_gateway: String,
+    pub program: ProgramInputs,
+    /// code ids of stored contracts. `None` if every wasm is uploaded
+    pub code_ids: Option<CodeIds>,
+    pub wasm: WasmInputs,
+    pub erc20: Erc20Inputs,
+    pub cw20: Cw20Inputs,
+    pub ownership: Option<OwnershipInputs>,
+    pub authorizations: AuthorizationsInputs,
+    /// label of the zk authorization created by setup-auth
+    pub zk_authorization_label: String,
+}
+
+/// contents of the neutron inputs file. unset chain constants fall back
+/// to the ones of the `network` profile.
+#[derive(Debug, Clone, Deserialize)]
+struct InputsFile {
+    network: String,
+    grpc_url: Option<String>,
+    grpc_port: Option<String>,
+    chain_id: Option<String>,
+    verification_gateway: Option<String>,
+    program: ProgramInputs,
+    code_ids: Option<CodeIds>,
+    #[serde(default)]
+    wasm: WasmInputs,
+    erc20: Erc20Inputs,
+    cw20: Cw20Inputs,
+    ownership: Option<OwnershipInputs>,
+    #[serde(default)]
+    authorizations: AuthorizationsInputs,
 }
 
+/// program identity the contract addresses are derived from. bump the
+/// version to deploy a fresh set of contracts.
 #[derive(Debug, Clone, Deserialize)]
+pub struct ProgramInputs {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodeIds {
     pub authorizations: u64,
     pub processor: u64,
     pub cw20: u64,
 }
 
-pub fn read_setup_inputs(cd: PathBuf) -> anyhow::Result<NeutronInputs> {
-    println!("reading inputs...");
+impl From<NetworkCodeIds> for CodeIds {
+    fn from(code_ids: NetworkCodeIds) -> Self {
+        Self {
+            authorizations: code_ids.authorizations,
+            processor: code_ids.processor,
+            cw20: code_ids.cw20,
+        }
+    }
+}
+
+/// wasm to upload before instantiating, per contract. contracts without
+/// a wasm path use the configured code ids.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WasmInputs {
+    pub authorizations: Option<WasmSource>,
+    pub processor: Option<WasmSource>,
+    pub cw20: Option<WasmSource>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WasmSource {
+    /// local wasm file to upload
+    pub path: Option<PathBuf>,
+    /// hex sha256 the stored code must match, uploaded or not
+    pub checksum: Option<String>,
+}
+
+impl WasmInputs {
+    fn sources(&self) -> [(&'static str, Option<&WasmSource>); 3] {
+        [
+            ("authorizations", self.authorizations.as_ref()),
+            ("processor", self.processor.as_ref()),
+            ("cw20", self.cw20.as_ref()),
+        ]
+    }
+
+    /// whether every contract is uploaded, so no code ids are needed
+    pub fn uploads_all(&self) -> bool {
+        self.sources()
+            .iter()
+            .all(|(_, source)| source.is_some_and(|source| source.path.is_some()))
+    }
+}
+
+impl WasmSource {
+    /// decoded pinned checksum
+    pub fn checksum(&self) -> anyhow::Result<Option<[u8; 32]>> {
+        self.checksum
+            .as_ref()
+            .map(|checksum| {
+                let bytes = hex::decode(checksum.trim_start_matches("0x"))?;
+                <[u8; 32]>::try_from(bytes)
+                    .map_err(|_| anyhow::anyhow!("wasm checksum {checksum} is not 32 bytes"))
+            })
+            .transpose()
+    }
+}
+
+/// source erc20 token the cw20 mirrors
+#[derive(Debug, Clone, Deserialize)]
+pub struct Erc20Inputs {
+    pub address: String,
+    pub decimals: u8,
+    /// index of the balances mapping storage slot
+    pub balance_slot: u64,
+    /// ethereum account the strategist proves the balance of
+    pub holder: Option<String>,
+}
+
+/// metadata of the mirror cw20
+#[derive(Debug, Clone, Deserialize)]
+pub struct Cw20Inputs {
+    pub name: String,
+    pub symbol: String,
+    /// defaults to the erc20 decimals
+    pub decimals: Option<u8>,
+    /// maximum total supply, in cw20 base units
+    pub cap: Option<Uint128>,
+    /// contract admin, able to migrate the cw20. defaults to the deployer
+    pub admin: Option<String>,
+    pub marketing: Option<MarketingInputs>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MarketingInputs {
+    pub project: Option<String>,
+    pub description: Option<String>,
+    /// address allowed to update the marketing info
+    pub marketing: Option<String>,
+    pub logo_url: Option<String>,
+}
+
+/// final owner the deployer hands the program over to, e.g. a dao or
+/// multisig. left unset, the deployer keeps ownership.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OwnershipInputs {
+    /// owner of the authorization contract and admin of the program contracts
+    pub owner: String,
+    /// addresses allowed to manage the authorizations next to the owner
+    #[serde(default)]
+    pub sub_owners: Vec<String>,
+}
+
+/// policies of the regular and zk mint authorizations. both default to
+/// the deployer alone, without call limit.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuthorizationsInputs {
+    #[serde(default)]
+    pub regular: AuthorizationPolicy,
+    #[serde(default)]
+    pub zk: AuthorizationPolicy,
+}
+
+/// who may execute an authorization, and for how long
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthorizationPolicy {
+    #[serde(default)]
+    pub mode: PolicyMode,
+    /// addresses of a permissioned authorization, defaults to the deployer
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    /// executions allowed per address, unlimited if unset
+    pub call_limit: Option<u64>,
+    /// validity once created, forever if unset. regular only
+    pub duration: Option<PolicyDuration>,
+    /// regular only
+    pub max_concurrent_executions: Option<u64>,
+    /// the only mint recipient allowed. regular only, as the zk mint
+    /// recipient is the one proven by the circuit
+    pub recipient: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyMode {
+    #[default]
+    Permissioned,
+    Permissionless,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyDuration {
+    Seconds(u64),
+    Blocks(u64),
+}
+
+impl AuthorizationPolicy {
+    fn validate(&self, authorization: &str) -> anyhow::Result<()> {
+        if self.mode == PolicyMode::Permissionless {
+            anyhow::ensure!(
+                self.addresses.is_empty() && self.call_limit.is_none(),
+                "the permissionless {authorization} authorization takes no addresses nor call limit"
+            );
+        }
+        if let Some(call_limit) = self.call_limit {
+            anyhow::ensure!(
+                call_limit > 0,
+                "{authorization} authorization call limit must not be zero"
+            );
+        }
+        if let Some(max) = self.max_concurrent_executions {
+            anyhow::ensure!(
+                max > 0,
+                "{authorization} authorization max concurrent executions must not be zero"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl AuthorizationsInputs {
+    fn validate(&self) -> anyhow::Result<()> {
+        self.regular.validate("regular")?;
+        self.zk.validate("zk")?;
+
+        // zk authorizations only carry a mode
+        let zk = &self.zk;
+        anyhow::ensure!(
+            zk.duration.is_none()
+                && zk.max_concurrent_executions.is_none()
+                && zk.recipient.is_none(),
+            "the zk authorization only supports a mode, addresses and call limit"
+        );
+
+        Ok(())
+    }
+}
+
+impl InputsFile {
+    fn resolve(self) -> anyhow::Result<NeutronInputs> {
+        let profile = NetworkProfile::by_name(&self.network)
+            .ok_or_else(|| anyhow::anyhow!("unknown network profile {}", self.network))?;
+
+        // fields the profile does not know must be set in the inputs file
+        let missing = |field: &str| {
+            anyhow::anyhow!(
+                "network {} has no default {field}, set it in the inputs file",
+                profile.name
+            )
+        };
+
+        Ok(NeutronInputs {
+            grpc_url: self
+                .grpc_url
+                .or(profile.grpc_url.map(String::from))
+                .ok_or_else(|| missing("grpc_url"))?,
+            grpc_port: self
+                .grpc_port
+                .or(profile.grpc_port.map(String::from))
+                .ok_or_else(|| missing("grpc_port"))?,
+            chain_id: self
+                .chain_id
+                .unwrap_or_else(|| profile.neutron_chain_id.to_string()),
+            verification_gateway: self
+                .verification_gateway
+                .or(profile.verification_gateway.map(String::from))
+                .ok_or_else(|| missing("verification_gateway"))?,
+            code_ids: match self.code_ids.or(profile.code_ids.map(CodeIds::from)) {
+                Some(code_ids) => Some(code_ids),
+                None if self.wasm.uploads_all() => None,
+                None => return Err(missing("code_ids")),
+            },
+            wasm: self.wasm,
+            network: self.network,
+            program: self.program,
+            erc20: self.erc20,
+            cw20: self.cw20,
+            ownership: self.ownership,
+            authorizations: self.authorizations,
+            zk_authorization_label: ZK_MINT_CW20_LABEL.to_string(),
+        })
+    }
+}
+
+/// erc20 sources deployed at once, each mirrored by its own program
+#[derive(Debug, Clone, Deserialize)]
+pub struct Manifest {
+    pub tokens: Vec<TokenInputs>,
+}
+
+/// erc20 source and mirror cw20 of a manifest token
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenInputs {
+    pub erc20: Erc20Inputs,
+    pub cw20: Cw20Inputs,
+}
+
+impl TokenInputs {
+    /// lowercase cw20 symbol, naming the files and labels of the token
+    pub fn id(&self) -> String {
+        self.cw20.symbol.to_lowercase()
+    }
+}
+
+impl NeutronInputs {
+    /// inputs deploying `token` as a program of its own: the erc20 and cw20
+    /// sections are replaced by the token ones, and the program name and
+    /// zk authorization label are suffixed with the token id
+    pub fn for_token(&self, token: &TokenInputs) -> anyhow::Result<Self> {
+        let id = token.id();
+        let inputs = Self {
+            program: ProgramInputs {
+                name: format!("{}-{id}", self.program.name),
+                version: self.program.version.clone(),
+            },
+            erc20: token.erc20.clone(),
+            cw20: token.cw20.clone(),
+            zk_authorization_label: format!("{ZK_MINT_CW20_LABEL}_{id}"),
+            ..self.clone()
+        };
+        inputs
+            .validate()
+            .map_err(|e| anyhow::anyhow!("invalid token {}: {e}", token.cw20.symbol))?;
+
+        Ok(inputs)
+    }
+
+    /// decimals of the mirror cw20
+    pub fn cw20_decimals(&self) -> u8 {
+        self.cw20.decimals.unwrap_or(self.erc20.decimals)
+    }
+
+    /// checks the cw20 metadata against the cw20-base rules and the
+    /// circuit params, before anything is deployed
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let name_len = self.cw20.name.len();
+        anyhow::ensure!(
+            (3..=50).contains(&name_len),
+            "cw20 name must be 3 to 50 bytes long, got {name_len}"
+        );
+
+        let symbol = &self.cw20.symbol;
+        anyhow::ensure!(
+            (3..=12).contains(&symbol.len())
+                && symbol.chars().all(|c| c.is_ascii_alphabetic() || c == '-'),
+            "cw20 symbol must be 3 to 12 letters or dashes, got {symbol}"
+        );
 
-    let input_dir = cd.join(format!("{INPUTS_DIR}/neutron_inputs.toml"));
-    let parameters = fs::read_to_string(input_dir)?;
+        // the circuit mints proven erc20 balances unscaled
+        let decimals = self.cw20_decimals();
+        anyhow::ensure!(
+            decimals <= MAX_CW20_DECIMALS,
+            "cw20 decimals must not exceed {MAX_CW20_DECIMALS}, got {decimals}"
+        );
+        anyhow::ensure!(
+            decimals == self.erc20.decimals,
+            "cw20 decimals ({decimals}) must equal the erc20 decimals ({}), \
+             the circuit mints proven balances unscaled",
+            self.erc20.decimals
+        );
+
+        if let Some(holder) = &self.erc20.holder {
+            let bytes = hex::decode(holder.trim_start_matches("0x")).unwrap_or_default();
+            anyhow::ensure!(
+                bytes.len() == 20,
+                "erc20 holder must be a hex encoded 20 byte address, got {holder}"
+            );
+        }
+
+        if let Some(cap) = self.cw20.cap {
+            anyhow::ensure!(!cap.is_zero(), "cw20 mint cap must not be zero");
+        }
+
+        for (contract, source) in self.wasm.sources() {
+            if let Some(source) = source {
+                source
+                    .checksum()
+                    .map_err(|e| anyhow::anyhow!("invalid {contract} wasm checksum: {e}"))?;
+            }
+        }
+
+        self.authorizations.validate()?;
+
+        // the deployer can only hand over a cw20 it is the admin of
+        if let (Some(ownership), Some(admin)) = (&self.ownership, &self.cw20.admin) {
+            anyhow::ensure!(
+                admin == &ownership.owner,
+                "cw20 admin {admin} differs from the final owner {}",
+                ownership.owner
+            );
+        }
+
+        Ok(())
+    }
+}
 
-    let neutron_inputs: NeutronInputs = toml::from_str(&parameters)?;
+pub fn read_setup_inputs(path: &Path) -> anyhow::Result<NeutronInputs> {
+    println!("reading inputs from {path:?}...");
+
+    let parameters = fs::read_to_string(path)?;
+
+    let neutron_inputs = parse_setup_inputs(&parameters)?;
 
     println!("neutron inputs from step: {:?}", neutron_inputs);
 
     Ok(neutron_inputs)
 }
+
+pub fn read_manifest(path: &Path) -> anyhow::Result<Manifest> {
+    println!("reading token manifest from {path:?}...");
+
+    let contents = fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("failed to read the token manifest {path:?}: {e}"))?;
+
+    parse_manifest(&contents)
+}
+
+/// parses the manifest, whose tokens must have distinct ids
+pub fn parse_manifest(contents: &str) -> anyhow::Result<Manifest> {
+    let manifest: Manifest = toml::from_str(contents)?;
+    anyhow::ensure!(
+        !manifest.tokens.is_empty(),
+        "token manifest lists no tokens"
+    );
+
+    let mut ids: Vec<String> = manifest.tokens.iter().map(TokenInputs::id).collect();
+    ids.sort();
+    if let Some(pair) = ids.windows(2).find(|pair| pair[0] == pair[1]) {
+        anyhow::bail!("token manifest lists the {} symbol twice", pair[0]);
+    }
+
+    Ok(manifest)
+}
+
+/// parses and validates the inputs file contents
+pub fn parse_setup_inputs(contents: &str) -> anyhow::Result<NeutronInputs> {
+    let inputs_file: InputsFile = toml::from_str(contents)?;
+
+    let neutron_inputs = inputs_file.resolve()?;
+    neutron_inputs.validate()?;
+
+    Ok(neutron_inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEUTRON_INPUTS: &str = include_str!("../inputs/neutron_inputs.toml");
+    const TOKENS_MANIFEST: &str = include_str!("../inputs/tokens_manifest.toml");
+
+    fn inputs() -> NeutronInputs {
+        parse_setup_inputs(NEUTRON_INPUTS).unwrap()
+    }
+
+    #[test]
+    fn test_default_inputs() {
+        let inputs = inputs();
+        inputs.validate().unwrap();
+
+        assert_eq!(inputs.cw20_decimals(), inputs.erc20.decimals);
+    }
+
+    #[test]
+    fn test_network_profile_defaults() {
+        let inputs = inputs();
+        assert_eq!(inputs.chain_id, "neutron-1");
+        assert_eq!(inputs.code_ids.unwrap().processor, 3934);
+
+        // local chains have no known code ids or gateway
+        let local = NEUTRON_INPUTS.replace(r#"network = "mainnet""#, r#"network = "local""#);
+        assert!(parse_setup_inputs(&local).is_err());
+
+        let overrides = r#"
+grpc_url = "http://localhost"
+grpc_port = "9090"
+verification_gateway = "neutron1gateway"
+"#;
+        let code_ids = r#"
+[code_ids]
+authorizations = 1
+processor = 2
+cw20 = 3
+"#;
+        let local = format!("{overrides}{local}{code_ids}");
+        let inputs = parse_setup_inputs(&local).unwrap();
+        assert_eq!(inputs.chain_id, "localneutron-1");
+        assert_eq!(inputs.code_ids.unwrap().cw20, 3);
+
+        // uploading every wasm needs no code ids
+        let wasm = r#"
+[wasm.authorizations]
+path = "wasm/valence_authorization.wasm"
+[wasm.processor]
+path = "wasm/valence_processor.wasm"
+[wasm.cw20]
+path = "wasm/cw20_base.wasm"
+checksum = "0x0000000000000000000000000000000000000000000000000000000000000000"
+"#;
+        let local = format!(
+            "{overrides}{}{wasm}",
+            NEUTRON_INPUTS.replace(r#"network = "mainnet""#, r#"network = "local""#)
+        );
+        let inputs = parse_setup_inputs(&local).unwrap();
+        assert!(inputs.code_ids.is_none());
+
+        let invalid = local.replace("0x00000000", "0x");
+        assert!(parse_setup_inputs(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_validate_cw20_inputs() {
+        let mut invalid = inputs();
+        invalid.cw20.symbol = "v$USDC".to_string();
+        assert!(invalid.validate().is_err());
+
+        // would mint 6 decimals erc20 balances as 4 or 18 decimals amounts
+        for decimals in [4, 18, 19] {
+            let mut invalid = inputs();
+            invalid.cw20.decimals = Some(decimals);
+            assert!(invalid.validate().is_err());
+        }
+
+        let mut invalid = inputs();
+        invalid.erc20.holder = Some("0x8d41bb".to_string());
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = inputs();
+        invalid.cw20.cap = Some(Uint128::zero());
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = inputs();
+        invalid.cw20.admin = Some("neutron1admin".to_string());
+        invalid.ownership = Some(OwnershipInputs {
+            owner: "neutron1dao".to_string(),
+            sub_owners: vec![],
+        });
+        assert!(invalid.validate().is_err());
+
+        let mut valid = inputs();
+        valid.cw20.decimals = Some(6);
+        valid.validate().unwrap();
+    }
+
+    #[test]
+    fn test_authorization_policies() {
+        let policies = r#"
+[authorizations.regular]
+addresses = ["neutron1a", "neutron1b"]
+call_limit = 10
+duration = { seconds = 86400 }
+recipient = "neutron1recipient"
+
+[authorizations.zk]
+mode = "permissionless"
+"#;
+        let inputs = parse_setup_inputs(&format!("{NEUTRON_INPUTS}{policies}")).unwrap();
+        assert_eq!(inputs.authorizations.regular.addresses.len(), 2);
+        assert_eq!(inputs.authorizations.zk.mode, PolicyMode::Permissionless);
+
+        let mut invalid = inputs.clone();
+        invalid.authorizations.zk.recipient = Some("neutron1recipient".to_string());
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = inputs;
+        invalid.authorizations.zk.call_limit = Some(1);
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_token_manifest() {
+        let manifest = parse_manifest(TOKENS_MANIFEST).unwrap();
+        let inputs = inputs();
+
+        for token in &manifest.tokens {
+            let token_inputs = inputs.for_token(token).unwrap();
+            let id = token.id();
+
+            assert_eq!(token_inputs.erc20.address, token.erc20.address);
+            assert_eq!(token_inputs.cw20.symbol, token.cw20.symbol);
+            assert_eq!(
+                token_inputs.program.name,
+                format!("{}-{id}", inputs.program.name)
+            );
+            assert_eq!(
+                token_inputs.zk_authorization_label,
+                format!("zk_mint_cw20_{id}")
+            );
+        }
+
+        // the token ids name the artifacts of each token
+        let duplicate = format!(
+            "{TOKENS_MANIFEST}\n{}",
+            &TOKENS_MANIFEST[TOKENS_MANIFEST.find("[[tokens]]").unwrap()..]
+        );
+        assert!(parse_manifest(&duplicate).is_err());
+        assert!(parse_manifest("tokens = []").is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use cosmrs::proto::cosmos::base::abci::v1beta1::TxResponse;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::steps::{CodeIds, InstantiationOutputs};
+
+/// transactions broadcast during a run, shared by the broadcasters and
+/// the deployment report
+pub type TxLog = Arc<Mutex<Vec<TxRecord>>>;
+
+/// included transaction of a deploy step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxRecord {
+    /// what the transaction did, e.g. `execute on neutron1...`
+    pub description: String,
+    pub hash: String,
+    pub height: i64,
+    pub gas_wanted: i64,
+    pub gas_used: i64,
+    /// fee paid, e.g. `5000untrn`, from the `tx` event
+    pub fee: String,
+    /// block time of the inclusion
+    pub timestamp: String,
+}
+
+impl TxRecord {
+    pub fn new(description: String, response: &TxResponse) -> Self {
+        let fee = response
+            .events
+            .iter()
+            .filter(|event| event.r#type == "tx")
+            .flat_map(|event| &event.attributes)
+            .find(|attribute| attribute.key == "fee")
+            .map(|attribute| attribute.value.clone())
+            .unwrap_or_default();
+
+        Self {
+            description,
+            hash: response.txhash.clone(),
+            height: response.height,
+            gas_wanted: response.gas_wanted,
+            gas_used: response.gas_used,
+            fee,
+            timestamp: response.timestamp.clone(),
+        }
+    }
+}
+
+/// audit record of a deploy command, written whether it succeeded or not
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeploymentReport {
+    pub command: String,
+    /// unix timestamps of the run
+    pub started_at: u64,
+    pub finished_at: u64,
+    /// error the command failed with, if any
+    pub error: Option<String>,
+    pub inputs: PathBuf,
+    /// hex encoded sha256 of the inputs file
+    pub inputs_sha256: String,
+    /// token manifest of a multi-token deployment
+    #[serde(default)]
+    pub manifest: Option<PathBuf>,
+    /// hex encoded sha256 of the token manifest
+    #[serde(default)]
+    pub manifest_sha256: Option<String>,
+    pub code_ids: Option<CodeIds>,
+    pub contracts: Option<InstantiationOutputs>,
+    pub coprocessor_app_id: Option<String>,
+    pub circuit_params_hash: Option<String>,
+    /// hex encoded bytes32 hash of the sp1 verifying key of the app
+    pub vk_hash: Option<String>,
+    pub zk_authorization_label: Option<String>,
+    pub transactions: Vec<TxRecord>,
+}
+
+impl DeploymentReport {
+    /// appends the report to the ones of the earlier runs at `path`
+    pub fn append(&self, path: &Path) -> anyhow::Result<()> {
+        let mut reports: Vec<Self> = if path.exists() {
+            let contents = fs::read_to_string(path)?;
+            serde_json::from_str(&contents)
+                .map_err(|e| anyhow::anyhow!("corrupted deployment report {path:?}: {e}"))?
+        } else {
+            vec![]
+        };
+        reports.push(self.clone());
+
+        println!("writing deployment report to: {path:?}");
+
+        fs::write(path, serde_json::to_string_pretty(&reports)?)?;
+
+        Ok(())
+    }
+}
+
+pub fn unix_now() -> anyhow::Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
+
+/// hex encoded sha256 of the file at `path`
+pub fn file_sha256(path: &Path) -> anyhow::Result<String> {
+    let contents = fs::read(path).map_err(|e| anyhow::anyhow!("failed to read {path:?}: {e}"))?;
+
+    Ok(hex::encode(Sha256::digest(contents)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn test_append_report() {
+        let path = env::temp_dir().join(format!("deploy-report-{}.json", std::process::id()));
+        let report = DeploymentReport {
+            command: "All".to_string(),
+            started_at: 1,
+            finished_at: 2,
+            error: None,
+            inputs: PathBuf::from("neutron_inputs.toml"),
+            inputs_sha256: "00".to_string(),
+            manifest: None,
+            manifest_sha256: None,
+            code_ids: None,
+            contracts: None,
+            coprocessor_app_id: None,
+            circuit_params_hash: None,
+            vk_hash: None,
+            zk_authorization_label: None,
+            transactions: vec![TxRecord::new(
+                "execute".to_string(),
+                &TxResponse {
+                    txhash: "ABCD".to_string(),
+                    height: 10,
+                    gas_used: 100,
+                    ..Default::default()
+                },
+            )],
+        };
+
+        report.append(&path).unwrap();
+        report.append(&path).unwrap();
+
+        let reports: Vec<DeploymentReport> =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[1].transactions[0].hash, "ABCD");
+        assert_eq!(reports[1].transactions[0].height, 10);
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,84 +1,178 @@
-use common::{NeutronStrategyConfig, REGULAR_MINT_CW20_LABEL, ZK_MINT_CW20_LABEL};
-use cosmwasm_std::Binary;
-use sp1_sdk::{HashableKey, SP1VerifyingKey};
+use common::{NeutronStrategyConfig, REGULAR_MINT_CW20_LABEL};
+use cosmwasm_std::{Binary, Uint128, to_json_binary};
+use serde_json::json;
+use storage_proof_core::mint::{CW20_MINT_MSG_NAME, cw20_mint_subroutine};
 use valence_authorization_utils::{
-    authorization::{AuthorizationModeInfo, PermissionTypeInfo},
-    authorization_message::{Message, MessageDetails, MessageType},
-    builders::{AtomicSubroutineBuilder, AuthorizationBuilder},
-    domain::Domain,
-    function::AtomicFunction,
+    authorization::{
+        AtomicSubroutine, AuthorizationDuration, AuthorizationInfo, AuthorizationModeInfo,
+        PermissionTypeInfo, Subroutine,
+    },
+    authorization_message::ParamRestriction,
+    builders::AuthorizationBuilder,
     zk_authorization::ZkAuthorizationInfo,
 };
-use valence_domain_clients::{
-    clients::{coprocessor::CoprocessorClient, neutron::NeutronClient},
-    coprocessor::base_client::CoprocessorBaseClient,
-    cosmos::{base_client::BaseClient, grpc_client::GrpcSigningClient, wasm_client::WasmClient},
+
+use crate::steps::{
+    AuthorizationPolicy, AuthorizationsInputs, Broadcaster, Checkpoint, PolicyDuration, PolicyMode,
+    verify::find_authorization,
 };
-use valence_library_utils::LibraryAccountType;
 
+/// creates the regular and zk cw20 mint authorizations, skipping the ones
+/// the checkpoint records as created or that exist on chain. exported
+/// proposals are recorded once observed on chain, after their execution.
 pub async fn setup_authorizations(
-    neutron_client: &NeutronClient,
+    broadcaster: &dyn Broadcaster,
     ntrn_strategy_config: &NeutronStrategyConfig,
+    policies: &AuthorizationsInputs,
+    checkpoint: &mut Checkpoint,
+    broadcasts: bool,
 ) -> anyhow::Result<()> {
     println!("setting up authorizations...");
-    let my_address = neutron_client
-        .get_signing_client()
-        .await?
-        .address
-        .to_string();
+    let my_address = broadcaster.sender().await?;
 
     println!("my address: {my_address}");
 
-    let authorization_permissioned_mode =
-        AuthorizationModeInfo::Permissioned(PermissionTypeInfo::WithoutCallLimit(vec![
-            my_address.to_string(),
-        ]));
+    if !checkpoint.regular_authorization
+        && on_chain(
+            broadcaster,
+            ntrn_strategy_config,
+            false,
+            REGULAR_MINT_CW20_LABEL,
+        )
+        .await?
+    {
+        checkpoint.regular_authorization = true;
+        checkpoint.save()?;
+    }
+    let zk_label = &ntrn_strategy_config.zk_authorization_label;
+    if !checkpoint.zk_authorization
+        && on_chain(broadcaster, ntrn_strategy_config, true, zk_label).await?
+    {
+        checkpoint.zk_authorization = true;
+        checkpoint.save()?;
+    }
 
     // creating equivalent variants for cw20 minting for both zk and regular auth mode
-    create_regular_cw20_mint_authorization(
-        neutron_client,
-        ntrn_strategy_config,
-        authorization_permissioned_mode.clone(),
-    )
-    .await?;
+    if checkpoint.regular_authorization {
+        println!("regular cw20 mint authorization already created, skipping");
+    } else {
+        let authorization =
+            regular_mint_authorization(&policies.regular, &ntrn_strategy_config.cw20, &my_address)?;
+        create_regular_cw20_mint_authorization(broadcaster, ntrn_strategy_config, authorization)
+            .await?;
 
-    create_zk_cw20_mint_authorization(
-        neutron_client,
-        ntrn_strategy_config,
-        authorization_permissioned_mode,
-    )
-    .await?;
+        checkpoint.regular_authorization = broadcasts;
+        checkpoint.save()?;
+    }
+
+    if checkpoint.zk_authorization {
+        println!("zk cw20 mint authorization already created, skipping");
+    } else {
+        create_zk_cw20_mint_authorization(
+            broadcaster,
+            ntrn_strategy_config,
+            authorization_mode(&policies.zk, &my_address),
+        )
+        .await?;
+
+        checkpoint.zk_authorization = broadcasts;
+        checkpoint.save()?;
+    }
 
     Ok(())
 }
 
-async fn create_regular_cw20_mint_authorization(
-    neutron_client: &NeutronClient,
+/// whether the zk, or regular, `label` authorization exists on chain. the
+/// authorization contract of a dry run may not exist yet.
+async fn on_chain(
+    broadcaster: &dyn Broadcaster,
     cfg: &NeutronStrategyConfig,
-    authorization_mode: AuthorizationModeInfo,
-) -> anyhow::Result<()> {
-    let cw20_mint_function = AtomicFunction {
-        domain: Domain::Main,
-        message_details: MessageDetails {
-            message_type: MessageType::CosmwasmExecuteMsg,
-            message: Message {
-                name: "mint".to_string(),
-                params_restrictions: None,
-            },
-        },
-        contract_address: LibraryAccountType::Addr(cfg.cw20.to_string()),
+    zk: bool,
+    label: &str,
+) -> anyhow::Result<bool> {
+    if broadcaster
+        .contract_info(&cfg.authorizations)
+        .await?
+        .is_none()
+    {
+        return Ok(false);
+    }
+
+    Ok(
+        find_authorization(broadcaster, &cfg.authorizations, zk, label)
+            .await?
+            .is_some(),
+    )
+}
+
+/// mode of an authorization following `policy`. permissioned policies
+/// without addresses are executable by the deployer only.
+pub fn authorization_mode(policy: &AuthorizationPolicy, deployer: &str) -> AuthorizationModeInfo {
+    if policy.mode == PolicyMode::Permissionless {
+        return AuthorizationModeInfo::Permissionless;
+    }
+
+    let addresses = if policy.addresses.is_empty() {
+        vec![deployer.to_string()]
+    } else {
+        policy.addresses.clone()
+    };
+
+    let permission = match policy.call_limit {
+        Some(call_limit) => PermissionTypeInfo::WithCallLimit(
+            addresses
+                .into_iter()
+                .map(|addr| (addr, Uint128::from(call_limit)))
+                .collect(),
+        ),
+        None => PermissionTypeInfo::WithoutCallLimit(addresses),
     };
 
-    let subroutine_mint_cw20 = AtomicSubroutineBuilder::new()
-        .with_function(cw20_mint_function)
-        .build();
+    AuthorizationModeInfo::Permissioned(permission)
+}
+
+/// regular cw20 mint authorization following `policy`
+fn regular_mint_authorization(
+    policy: &AuthorizationPolicy,
+    cw20: &str,
+    deployer: &str,
+) -> anyhow::Result<AuthorizationInfo> {
+    // the subroutine of the zk message, restricted to the policy recipient
+    let mut subroutine = cw20_mint_subroutine(cw20);
+    if let Some(recipient) = &policy.recipient {
+        let Subroutine::Atomic(AtomicSubroutine { functions, .. }) = &mut subroutine else {
+            anyhow::bail!("the cw20 mint subroutine is not atomic");
+        };
+        functions[0].message_details.message.params_restrictions =
+            Some(vec![ParamRestriction::MustBeValue(
+                vec![CW20_MINT_MSG_NAME.to_string(), "recipient".to_string()],
+                to_json_binary(recipient)?,
+            )]);
+    }
 
-    let authorization_cw20_mint = AuthorizationBuilder::new()
+    let mut builder = AuthorizationBuilder::new()
         .with_label(REGULAR_MINT_CW20_LABEL)
-        .with_mode(authorization_mode.clone())
-        .with_subroutine(subroutine_mint_cw20)
-        .build();
+        .with_mode(authorization_mode(policy, deployer))
+        .with_subroutine(subroutine);
+
+    if let Some(duration) = policy.duration {
+        builder = builder.with_duration(match duration {
+            PolicyDuration::Seconds(seconds) => AuthorizationDuration::Seconds(seconds),
+            PolicyDuration::Blocks(blocks) => AuthorizationDuration::Blocks(blocks),
+        });
+    }
+    if let Some(max) = policy.max_concurrent_executions {
+        builder = builder.with_max_concurrent_executions(max);
+    }
+
+    Ok(builder.build())
+}
 
+async fn create_regular_cw20_mint_authorization(
+    broadcaster: &dyn Broadcaster,
+    cfg: &NeutronStrategyConfig,
+    authorization_cw20_mint: AuthorizationInfo,
+) -> anyhow::Result<()> {
     let regular_authorizations = vec![authorization_cw20_mint];
 
     let create_authorizations = valence_authorization_utils::msg::ExecuteMsg::PermissionedAction(
@@ -87,11 +181,8 @@ async fn create_regular_cw20_mint_authorization(
         },
     );
 
-    let create_authorizations_rx = neutron_client
-        .execute_wasm(&cfg.authorizations, create_authorizations, vec![], None)
-        .await?;
-    neutron_client
-        .poll_for_tx(&create_authorizations_rx.hash)
+    broadcaster
+        .execute(&cfg.authorizations, json!(create_authorizations))
         .await?;
 
     println!(
@@ -100,23 +191,17 @@ async fn create_regular_cw20_mint_authorization(
     Ok(())
 }
 
-async fn create_zk_cw20_mint_authorization(
-    neutron_client: &NeutronClient,
+/// creates the `cfg.zk_authorization_label` authorization, verifying
+/// proofs of the `cfg.coprocessor_app_id` circuit
+pub async fn create_zk_cw20_mint_authorization(
+    broadcaster: &dyn Broadcaster,
     cfg: &NeutronStrategyConfig,
     authorization_mode: AuthorizationModeInfo,
 ) -> anyhow::Result<()> {
-    let coprocessor_client = CoprocessorClient::default();
-    let program_vk = coprocessor_client.get_vk(&cfg.coprocessor_app_id).await?;
+    let program_vk = broadcaster.program_vk(&cfg.coprocessor_app_id).await?;
 
-    let sp1_program_vk: SP1VerifyingKey = bincode::deserialize(&program_vk)?;
-
-    let zk_authorization = ZkAuthorizationInfo {
-        label: ZK_MINT_CW20_LABEL.to_string(),
-        mode: authorization_mode,
-        registry: 0,
-        vk: Binary::from(sp1_program_vk.bytes32().as_bytes()),
-        validate_last_block_execution: false,
-    };
+    let zk_authorization =
+        zk_mint_authorization(&cfg.zk_authorization_label, authorization_mode, program_vk);
 
     let create_zk_authorization = valence_authorization_utils::msg::ExecuteMsg::PermissionedAction(
         valence_authorization_utils::msg::PermissionedMsg::CreateZkAuthorizations {
@@ -126,13 +211,190 @@ async fn create_zk_cw20_mint_authorization(
 
     println!("creating ZK authorization...");
 
-    let create_zk_auth_rx = neutron_client
-        .execute_wasm(&cfg.authorizations, create_zk_authorization, vec![], None)
+    broadcaster
+        .execute(&cfg.authorizations, json!(create_zk_authorization))
         .await?;
 
-    neutron_client.poll_for_tx(&create_zk_auth_rx.hash).await?;
-
-    println!("ZK Authorization created successfully");
+    println!(
+        "ZK Authorization {} created successfully",
+        cfg.zk_authorization_label
+    );
 
     Ok(())
 }
+
+/// zk cw20 mint authorization `label`, verifying proofs against `vk`
+fn zk_mint_authorization(
+    label: &str,
+    authorization_mode: AuthorizationModeInfo,
+    vk: Binary,
+) -> ZkAuthorizationInfo {
+    ZkAuthorizationInfo {
+        label: label.to_string(),
+        mode: authorization_mode,
+        registry: 0,
+        vk,
+        validate_last_block_execution: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::ZK_MINT_CW20_LABEL;
+    use serde_json::Value;
+    use storage_proof_circuit::{MintStatement, build_zk_msg};
+    use storage_proof_core::consts::CW20_ADDR;
+    use valence_authorization_utils::authorization::AuthorizationMsg;
+
+    use super::*;
+
+    const DEPLOYER: &str = "neutron1deployer";
+    const CW20: &str = "neutron1cw20";
+
+    fn regular_json(policy: &AuthorizationPolicy) -> Value {
+        json!(regular_mint_authorization(policy, CW20, DEPLOYER).unwrap())
+    }
+
+    /// subroutine of the zk messages the circuit emits
+    fn zk_subroutine() -> Subroutine {
+        let statement = MintStatement {
+            recipient: "neutron1recipient".to_string(),
+            amount: 1,
+            block_number: 0,
+        };
+        let AuthorizationMsg::EnqueueMsgs { subroutine, .. } = build_zk_msg(&statement).message
+        else {
+            panic!("the circuit must emit an enqueue msgs message");
+        };
+
+        subroutine
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let authorization = regular_json(&AuthorizationPolicy::default());
+
+        assert_eq!(authorization["label"], REGULAR_MINT_CW20_LABEL);
+        assert_eq!(
+            authorization["mode"],
+            json!({ "permissioned": { "without_call_limit": [DEPLOYER] } })
+        );
+        assert_eq!(authorization["duration"], json!("forever"));
+        assert_eq!(authorization["max_concurrent_executions"], Value::Null);
+    }
+
+    #[test]
+    fn test_permissionless_policy() {
+        let policy = AuthorizationPolicy {
+            mode: PolicyMode::Permissionless,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            json!(authorization_mode(&policy, DEPLOYER)),
+            json!("permissionless")
+        );
+    }
+
+    #[test]
+    fn test_call_limit_policy() {
+        let policy = AuthorizationPolicy {
+            addresses: vec!["neutron1a".to_string(), "neutron1b".to_string()],
+            call_limit: Some(10),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            regular_json(&policy)["mode"],
+            json!({
+                "permissioned": {
+                    "with_call_limit": [["neutron1a", "10"], ["neutron1b", "10"]]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_duration_and_concurrency_policy() {
+        let policy = AuthorizationPolicy {
+            duration: Some(PolicyDuration::Blocks(1000)),
+            max_concurrent_executions: Some(2),
+            ..Default::default()
+        };
+
+        let authorization = regular_json(&policy);
+        assert_eq!(authorization["duration"], json!({ "blocks": 1000 }));
+        assert_eq!(authorization["max_concurrent_executions"], json!(2));
+    }
+
+    #[test]
+    fn test_subroutine_matches_zk_message() {
+        let authorization =
+            regular_mint_authorization(&AuthorizationPolicy::default(), CW20_ADDR, DEPLOYER)
+                .unwrap();
+
+        assert_eq!(authorization.subroutine, zk_subroutine());
+    }
+
+    #[test]
+    fn test_recipient_policy() {
+        let policy = AuthorizationPolicy {
+            recipient: Some("neutron1recipient".to_string()),
+            ..Default::default()
+        };
+        let authorization = regular_mint_authorization(&policy, CW20_ADDR, DEPLOYER).unwrap();
+
+        // the zk message subroutine, with the recipient restriction only
+        let Subroutine::Atomic(mut expected) = zk_subroutine() else {
+            panic!("the circuit must emit an atomic subroutine");
+        };
+        expected.functions[0]
+            .message_details
+            .message
+            .params_restrictions = Some(vec![ParamRestriction::MustBeValue(
+            vec![CW20_MINT_MSG_NAME.to_string(), "recipient".to_string()],
+            to_json_binary("neutron1recipient").unwrap(),
+        )]);
+        assert_eq!(authorization.subroutine, Subroutine::Atomic(expected));
+    }
+
+    #[test]
+    fn test_zk_authorization_policies() {
+        let vk = Binary::from([1u8; 32].as_slice());
+        let zk_json = |policy: &AuthorizationPolicy| {
+            let mode = authorization_mode(policy, DEPLOYER);
+            json!(zk_mint_authorization(ZK_MINT_CW20_LABEL, mode, vk.clone()))
+        };
+
+        let permissionless = AuthorizationPolicy {
+            mode: PolicyMode::Permissionless,
+            ..Default::default()
+        };
+        assert_eq!(
+            zk_json(&permissionless),
+            json!({
+                "label": ZK_MINT_CW20_LABEL,
+                "mode": "permissionless",
+                "registry": 0,
+                "vk": vk.to_base64(),
+                "validate_last_block_execution": false,
+            })
+        );
+
+        let call_limit = AuthorizationPolicy {
+            addresses: vec!["neutron1a".to_string()],
+            call_limit: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            zk_json(&call_limit),
+            json!({
+                "label": ZK_MINT_CW20_LABEL,
+                "mode": { "permissioned": { "with_call_limit": [["neutron1a", "3"]] } },
+                "registry": 0,
+                "vk": vk.to_base64(),
+                "validate_last_block_execution": false,
+            })
+        );
+    }
+}
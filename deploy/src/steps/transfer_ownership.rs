@@ -0,0 +1,234 @@
+use cosmwasm_std::Addr;
+use cw_ownable::{Action, Ownership};
+use serde_json::{Value, json};
+use valence_authorization_utils::msg::{ExecuteMsg, OwnerMsg, QueryMsg};
+
+use crate::steps::{Broadcaster, InstantiationOutputs, OwnershipInputs};
+
+/// adds the configured sub owners to the authorization contract, then
+/// hands it over to the final owner, together with the wasm admin of the
+/// authorization, processor and cw20 contracts. the authorization
+/// ownership transfer only completes once the owner accepts it. every
+/// step already observed on chain is skipped, so it can be rerun.
+pub async fn transfer_ownership(
+    broadcaster: &dyn Broadcaster,
+    contracts: &InstantiationOutputs,
+    ownership: &OwnershipInputs,
+) -> anyhow::Result<()> {
+    println!("transferring ownership to {}...", ownership.owner);
+
+    // a dry run plans for contracts that are not instantiated yet
+    let instantiated = broadcaster
+        .contract_info(&contracts.authorizations)
+        .await?
+        .is_some();
+    let (sub_owners, transferred) = match instantiated {
+        true => (
+            query_sub_owners(broadcaster, &contracts.authorizations).await?,
+            is_owner_or_pending(
+                &query_ownership(broadcaster, &contracts.authorizations).await?,
+                &ownership.owner,
+            ),
+        ),
+        false => (vec![], false),
+    };
+
+    // sub owners can only be added while the deployer still owns the contract
+    for msg in ownership_msgs(ownership, &sub_owners, transferred)? {
+        broadcaster.execute(&contracts.authorizations, msg).await?;
+    }
+
+    if transferred {
+        println!(
+            "authorization ownership already transferred to {}",
+            ownership.owner
+        );
+    } else {
+        println!(
+            "authorization ownership transfer to {} proposed",
+            ownership.owner
+        );
+    }
+
+    // a configured cw20 admin is checked to be the owner already
+    for (label, contract) in admin_contracts(contracts) {
+        let admin = broadcaster
+            .contract_info(contract)
+            .await?
+            .map(|info| info.admin);
+
+        if admin.as_deref() == Some(ownership.owner.as_str()) {
+            println!("{label} admin already {}", ownership.owner);
+            continue;
+        }
+
+        broadcaster.update_admin(contract, &ownership.owner).await?;
+        println!("{label} admin set to {}", ownership.owner);
+    }
+
+    Ok(())
+}
+
+/// checks the authorization owner, or the pending one, its sub owners and
+/// the wasm admins against the configured ownership
+pub async fn verify_ownership(
+    broadcaster: &dyn Broadcaster,
+    contracts: &InstantiationOutputs,
+    ownership: &OwnershipInputs,
+) -> anyhow::Result<()> {
+    let owner = &ownership.owner;
+
+    let current = query_ownership(broadcaster, &contracts.authorizations).await?;
+    if is_addr(&current.owner, owner) {
+        println!("authorization contract owned by {owner}");
+    } else if is_addr(&current.pending_owner, owner) {
+        println!("authorization ownership pending, {owner} must accept it");
+    } else {
+        anyhow::bail!(
+            "authorization contract is owned by {:?}, pending {:?}, expected {owner}",
+            current.owner,
+            current.pending_owner
+        );
+    }
+
+    let sub_owners = query_sub_owners(broadcaster, &contracts.authorizations).await?;
+    for sub_owner in &ownership.sub_owners {
+        anyhow::ensure!(
+            sub_owners
+                .iter()
+                .any(|addr| addr.as_str() == sub_owner.as_str()),
+            "{sub_owner} is not a sub owner of the authorization contract"
+        );
+    }
+
+    for (label, contract) in admin_contracts(contracts) {
+        let info = broadcaster
+            .contract_info(contract)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("no {label} contract at {contract}"))?;
+        anyhow::ensure!(
+            info.admin == *owner,
+            "{label} admin is {:?}, expected {owner}",
+            info.admin
+        );
+    }
+
+    println!("ownership verified");
+
+    Ok(())
+}
+
+/// contracts whose wasm admin, able to migrate them, goes to the owner
+fn admin_contracts(contracts: &InstantiationOutputs) -> [(&'static str, &str); 3] {
+    [
+        ("authorization", &contracts.authorizations),
+        ("processor", &contracts.processor),
+        ("cw20", &contracts.cw20),
+    ]
+}
+
+async fn query_ownership(
+    broadcaster: &dyn Broadcaster,
+    authorizations: &str,
+) -> anyhow::Result<Ownership<Addr>> {
+    let response = broadcaster
+        .query(authorizations, json!(QueryMsg::Ownership {}))
+        .await?;
+
+    Ok(serde_json::from_value(response)?)
+}
+
+async fn query_sub_owners(
+    broadcaster: &dyn Broadcaster,
+    authorizations: &str,
+) -> anyhow::Result<Vec<Addr>> {
+    let response = broadcaster
+        .query(authorizations, json!(QueryMsg::SubOwners {}))
+        .await?;
+
+    Ok(serde_json::from_value(response)?)
+}
+
+fn is_addr(addr: &Option<Addr>, expected: &str) -> bool {
+    addr.as_ref().is_some_and(|addr| addr.as_str() == expected)
+}
+
+fn is_owner_or_pending(ownership: &Ownership<Addr>, owner: &str) -> bool {
+    is_addr(&ownership.owner, owner) || is_addr(&ownership.pending_owner, owner)
+}
+
+/// authorization contract messages adding the missing sub owners and
+/// proposing the ownership transfer, unless already `transferred`, in order
+fn ownership_msgs(
+    ownership: &OwnershipInputs,
+    sub_owners: &[Addr],
+    transferred: bool,
+) -> anyhow::Result<Vec<Value>> {
+    let missing: Vec<&String> = ownership
+        .sub_owners
+        .iter()
+        .filter(|sub_owner| !sub_owners.iter().any(|addr| addr.as_str() == *sub_owner))
+        .collect();
+
+    anyhow::ensure!(
+        !transferred || missing.is_empty(),
+        "sub owners {missing:?} are missing, and only {} can add them now",
+        ownership.owner
+    );
+
+    let mut msgs: Vec<Value> = missing
+        .into_iter()
+        .map(|sub_owner| {
+            json!(ExecuteMsg::OwnerAction(OwnerMsg::AddSubOwner {
+                sub_owner: sub_owner.clone(),
+            }))
+        })
+        .collect();
+
+    if !transferred {
+        msgs.push(json!(ExecuteMsg::UpdateOwnership(
+            Action::TransferOwnership {
+                new_owner: ownership.owner.clone(),
+                expiry: None,
+            }
+        )));
+    }
+
+    Ok(msgs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ownership_msgs() {
+        let ownership = OwnershipInputs {
+            owner: "neutron1dao".to_string(),
+            sub_owners: vec!["neutron1ops".to_string(), "neutron1sec".to_string()],
+        };
+
+        let msgs = ownership_msgs(&ownership, &[Addr::unchecked("neutron1sec")], false).unwrap();
+        assert_eq!(
+            msgs,
+            vec![
+                json!({ "owner_action": { "add_sub_owner": { "sub_owner": "neutron1ops" } } }),
+                json!({
+                    "update_ownership": {
+                        "transfer_ownership": { "new_owner": "neutron1dao", "expiry": null }
+                    }
+                }),
+            ]
+        );
+
+        // rerun after the transfer: nothing left to do
+        let added = [
+            Addr::unchecked("neutron1ops"),
+            Addr::unchecked("neutron1sec"),
+        ];
+        assert!(ownership_msgs(&ownership, &added, true).unwrap().is_empty());
+
+        // the deployer can no longer add them
+        assert!(ownership_msgs(&ownership, &added[..1], true).is_err());
+    }
+}
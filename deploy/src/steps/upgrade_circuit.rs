@@ -0,0 +1,421 @@
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use common::NeutronStrategyConfig;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use valence_authorization_utils::{
+    authorization::{AuthorizationModeInfo, AuthorizationState},
+    msg::{ExecuteMsg, PermissionedMsg},
+};
+
+use crate::steps::{
+    Broadcaster, Checkpoint, setup_authorizations::create_zk_cw20_mint_authorization,
+    verify::find_authorization,
+};
+
+/// circuit replaced by an upgrade
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitRecord {
+    pub coprocessor_app_id: String,
+    pub circuit_params_hash: String,
+    pub zk_authorization_label: String,
+    /// hex encoded bytes32 of the sp1 verifying key
+    pub vk: String,
+    /// unix timestamp of the upgrade replacing the circuit
+    pub replaced_at: u64,
+}
+
+/// every circuit replaced by an upgrade, oldest first
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CircuitHistory {
+    #[serde(default)]
+    pub circuits: Vec<CircuitRecord>,
+}
+
+impl CircuitHistory {
+    /// loads the history at `path`, empty if there is none yet
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(path)?;
+        toml::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("corrupted circuit history {path:?}: {e}"))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        println!("writing circuit history to: {path:?}");
+
+        fs::write(path, toml::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    /// label of the zk authorization of the circuit replacing the current
+    /// one, versioning the `base` label created by setup-auth
+    pub fn next_zk_authorization_label(&self, base: &str) -> String {
+        // the first circuit is the one created by setup-auth
+        format!("{base}_v{}", self.circuits.len() + 2)
+    }
+}
+
+/// rotates the zk authorization to the pending upgrade of the checkpoint,
+/// then makes it the current circuit, returning the record of the replaced
+/// one. an exported proposal changes nothing until it executes, so the
+/// upgrade stays pending until a rerun observes it on chain.
+pub async fn upgrade_circuit(
+    broadcaster: &dyn Broadcaster,
+    previous: &NeutronStrategyConfig,
+    next: &NeutronStrategyConfig,
+    authorization_mode: AuthorizationModeInfo,
+    checkpoint: &mut Checkpoint,
+    broadcasts: bool,
+) -> anyhow::Result<Option<CircuitRecord>> {
+    let rotation = rotate_zk_authorization(
+        broadcaster,
+        previous,
+        next,
+        authorization_mode,
+        checkpoint,
+        broadcasts,
+    )
+    .await?;
+
+    let Some(record) = rotation else {
+        println!(
+            "the upgrade stays pending until the rotation is on chain, rerun upgrade-circuit then"
+        );
+        return Ok(None);
+    };
+
+    let pending = checkpoint
+        .pending_upgrade
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("no pending circuit upgrade"))?;
+    checkpoint.coprocessor_app = Some(pending.app);
+    checkpoint.zk_authorization_label = Some(pending.zk_authorization_label);
+    checkpoint.save()?;
+
+    Ok(Some(record))
+}
+
+/// creates the zk authorization of the `next` circuit and disables the one
+/// of the `previous` circuit, skipping what the checkpoint or the chain
+/// records as done. returns the record of the previous circuit once the
+/// rotation is broadcast or observed on chain.
+async fn rotate_zk_authorization(
+    broadcaster: &dyn Broadcaster,
+    previous: &NeutronStrategyConfig,
+    next: &NeutronStrategyConfig,
+    authorization_mode: AuthorizationModeInfo,
+    checkpoint: &mut Checkpoint,
+    broadcasts: bool,
+) -> anyhow::Result<Option<CircuitRecord>> {
+    let previous_vk = broadcaster.program_vk(&previous.coprocessor_app_id).await?;
+    let next_vk = broadcaster.program_vk(&next.coprocessor_app_id).await?;
+    anyhow::ensure!(
+        previous_vk != next_vk,
+        "app {} has the verifying key of the current circuit, nothing to upgrade",
+        next.coprocessor_app_id
+    );
+
+    let pending = checkpoint
+        .pending_upgrade
+        .as_mut()
+        .ok_or_else(|| anyhow::anyhow!("no pending circuit upgrade"))?;
+    let next_authorization = find_authorization(
+        broadcaster,
+        &next.authorizations,
+        true,
+        &next.zk_authorization_label,
+    )
+    .await?;
+    if next_authorization.is_some() {
+        pending.zk_authorization = true;
+    }
+
+    let created = pending.zk_authorization;
+    if created {
+        println!("{} already created, skipping", next.zk_authorization_label);
+    } else {
+        create_zk_cw20_mint_authorization(broadcaster, next, authorization_mode).await?;
+
+        // an exported proposal creates it once executed
+        pending.zk_authorization = broadcasts;
+    }
+    checkpoint.save()?;
+
+    let previous_authorization = find_authorization(
+        broadcaster,
+        &previous.authorizations,
+        true,
+        &previous.zk_authorization_label,
+    )
+    .await?;
+    let disabled = previous_authorization
+        .is_some_and(|authorization| authorization["state"] == json!(AuthorizationState::Disabled));
+
+    if disabled {
+        println!(
+            "{} already disabled, skipping",
+            previous.zk_authorization_label
+        );
+    } else {
+        // proofs of the replaced circuit must no longer mint
+        let disable = ExecuteMsg::PermissionedAction(PermissionedMsg::DisableZkAuthorization {
+            label: previous.zk_authorization_label.clone(),
+        });
+        broadcaster
+            .execute(&previous.authorizations, json!(disable))
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "failed to disable {}, it still accepts proofs of app {}: {e}",
+                    previous.zk_authorization_label,
+                    previous.coprocessor_app_id
+                )
+            })?;
+        println!("disabled {}", previous.zk_authorization_label);
+    }
+
+    if !broadcasts && !(created && disabled) {
+        return Ok(None);
+    }
+
+    Ok(Some(CircuitRecord {
+        coprocessor_app_id: previous.coprocessor_app_id.clone(),
+        circuit_params_hash: previous.circuit_params_hash.clone().unwrap_or_default(),
+        zk_authorization_label: previous.zk_authorization_label.clone(),
+        vk: hex::encode(previous_vk.as_slice()),
+        replaced_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, path::PathBuf};
+
+    use async_trait::async_trait;
+    use common::ZK_MINT_CW20_LABEL;
+    use cosmrs::proto::cosmwasm::wasm::v1::ContractInfo;
+    use cosmwasm_std::Binary;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::steps::{CoprocessorApp, PendingUpgrade, ProposalExport, ProposalFormat};
+
+    /// authorization contract holding `zk_authorizations`, with a verifying
+    /// key per app. it broadcasts nothing.
+    struct Chain {
+        zk_authorizations: Vec<Value>,
+    }
+
+    #[async_trait]
+    impl Broadcaster for Chain {
+        async fn sender(&self) -> anyhow::Result<String> {
+            Ok("neutron1deployer".to_string())
+        }
+
+        async fn predict_instantiate2_addr(
+            &self,
+            _code_id: u64,
+            _salt: &str,
+            _creator: &str,
+        ) -> anyhow::Result<String> {
+            unimplemented!()
+        }
+
+        async fn instantiate2(
+            &self,
+            _code_id: u64,
+            _label: &str,
+            _msg: Value,
+            _admin: Option<String>,
+            _salt: &str,
+        ) -> anyhow::Result<String> {
+            unimplemented!()
+        }
+
+        async fn contract_info(&self, _addr: &str) -> anyhow::Result<Option<ContractInfo>> {
+            Ok(Some(ContractInfo::default()))
+        }
+
+        async fn query(&self, _contract: &str, _msg: Value) -> anyhow::Result<Value> {
+            Ok(json!(self.zk_authorizations))
+        }
+
+        async fn execute(&self, contract: &str, _msg: Value) -> anyhow::Result<()> {
+            anyhow::bail!("unexpected broadcast on {contract}")
+        }
+
+        async fn update_admin(&self, contract: &str, _new_admin: &str) -> anyhow::Result<()> {
+            anyhow::bail!("unexpected broadcast on {contract}")
+        }
+
+        async fn store_code(&self, _wasm: Vec<u8>) -> anyhow::Result<u64> {
+            unimplemented!()
+        }
+
+        async fn code_checksum(&self, _code_id: u64) -> anyhow::Result<Vec<u8>> {
+            unimplemented!()
+        }
+
+        async fn program_vk(&self, app_id: &str) -> anyhow::Result<Binary> {
+            Ok(Binary::from(app_id.as_bytes()))
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("upgrade-{name}-{}", std::process::id()))
+    }
+
+    fn strategy_config(app: &str, label: &str) -> NeutronStrategyConfig {
+        NeutronStrategyConfig {
+            grpc_url: String::new(),
+            grpc_port: String::new(),
+            chain_id: String::new(),
+            authorizations: "neutron1authorizations".to_string(),
+            processor: String::new(),
+            cw20: String::new(),
+            coprocessor_app_id: app.to_string(),
+            circuit_params_hash: None,
+            zk_authorization_label: label.to_string(),
+            erc20: String::new(),
+            holder: String::new(),
+        }
+    }
+
+    /// checkpoint saved at `path` with an upgrade of app `old` to `new` pending
+    fn pending_checkpoint(path: &Path) -> Checkpoint {
+        let mut checkpoint = Checkpoint::load(path, true).unwrap();
+        checkpoint.coprocessor_app = Some(CoprocessorApp {
+            id: "old".to_string(),
+            circuit_params_hash: String::new(),
+        });
+        checkpoint.pending_upgrade = Some(PendingUpgrade {
+            app: CoprocessorApp {
+                id: "new".to_string(),
+                circuit_params_hash: String::new(),
+            },
+            zk_authorization_label: "zk_mint_cw20_v2".to_string(),
+            zk_authorization: false,
+        });
+        checkpoint.save().unwrap();
+
+        checkpoint
+    }
+
+    async fn upgrade(
+        broadcaster: &dyn Broadcaster,
+        checkpoint: &mut Checkpoint,
+        broadcasts: bool,
+    ) -> Option<CircuitRecord> {
+        upgrade_circuit(
+            broadcaster,
+            &strategy_config("old", ZK_MINT_CW20_LABEL),
+            &strategy_config("new", "zk_mint_cw20_v2"),
+            AuthorizationModeInfo::Permissionless,
+            checkpoint,
+            broadcasts,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_exported_upgrade_leaves_checkpoint() {
+        let checkpoint_path = temp_path("checkpoint");
+        let proposal_path = temp_path("proposal");
+        let mut checkpoint = pending_checkpoint(&checkpoint_path);
+        let saved = fs::read_to_string(&checkpoint_path).unwrap();
+
+        let chain = Chain {
+            zk_authorizations: vec![],
+        };
+        let proposal = ProposalExport::new(
+            Box::new(chain),
+            proposal_path.clone(),
+            ProposalFormat::DaoDao,
+            None,
+            None,
+            "upgrade".to_string(),
+        )
+        .unwrap();
+
+        assert!(upgrade(&proposal, &mut checkpoint, false).await.is_none());
+
+        // the create and disable messages are exported, nothing is recorded
+        let exported: Value =
+            serde_json::from_str(&fs::read_to_string(&proposal_path).unwrap()).unwrap();
+        assert_eq!(exported["propose"]["msgs"].as_array().unwrap().len(), 2);
+        assert_eq!(fs::read_to_string(&checkpoint_path).unwrap(), saved);
+        assert_eq!(checkpoint.coprocessor_app.as_ref().unwrap().id, "old");
+        assert!(
+            !checkpoint
+                .pending_upgrade
+                .as_ref()
+                .unwrap()
+                .zk_authorization
+        );
+
+        fs::remove_file(checkpoint_path).unwrap();
+        fs::remove_file(proposal_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_completes_once_on_chain() {
+        let checkpoint_path = temp_path("executed");
+        let mut checkpoint = pending_checkpoint(&checkpoint_path);
+
+        // the exported proposal executed: nothing is left to broadcast
+        let chain = Chain {
+            zk_authorizations: vec![
+                json!({ "label": ZK_MINT_CW20_LABEL, "state": AuthorizationState::Disabled }),
+                json!({ "label": "zk_mint_cw20_v2", "state": AuthorizationState::Enabled }),
+            ],
+        };
+
+        let record = upgrade(&chain, &mut checkpoint, false).await.unwrap();
+        assert_eq!(record.coprocessor_app_id, "old");
+        assert_eq!(record.zk_authorization_label, ZK_MINT_CW20_LABEL);
+
+        let checkpoint = Checkpoint::load(&checkpoint_path, false).unwrap();
+        assert_eq!(checkpoint.coprocessor_app.unwrap().id, "new");
+        assert_eq!(
+            checkpoint.zk_authorization_label.as_deref(),
+            Some("zk_mint_cw20_v2")
+        );
+        assert!(checkpoint.pending_upgrade.is_none());
+
+        fs::remove_file(checkpoint_path).unwrap();
+    }
+
+    #[test]
+    fn test_next_zk_authorization_label() {
+        let mut history = CircuitHistory::default();
+        assert_eq!(
+            history.next_zk_authorization_label(ZK_MINT_CW20_LABEL),
+            "zk_mint_cw20_v2"
+        );
+
+        history.circuits.push(CircuitRecord {
+            coprocessor_app_id: "app".to_string(),
+            circuit_params_hash: "hash".to_string(),
+            zk_authorization_label: ZK_MINT_CW20_LABEL.to_string(),
+            vk: "00".to_string(),
+            replaced_at: 0,
+        });
+        assert_eq!(
+            history.next_zk_authorization_label("zk_mint_cw20_vusdt"),
+            "zk_mint_cw20_vusdt_v3"
+        );
+
+        let history: CircuitHistory = toml::from_str(&toml::to_string(&history).unwrap()).unwrap();
+        assert_eq!(history.circuits.len(), 1);
+    }
+}
@@ -0,0 +1,300 @@
+use std::fs;
+
+use sha2::{Digest, Sha256};
+
+use crate::steps::{Broadcaster, Checkpoint, CodeIds, NeutronInputs, WasmSource};
+
+/// uploads the configured wasm and checks every pinned checksum against
+/// the stored code, returning the code ids to instantiate. every upload is
+/// checkpointed as soon as it is confirmed.
+pub async fn upload_contracts(
+    broadcaster: &dyn Broadcaster,
+    neutron_inputs: &NeutronInputs,
+    checkpoint: &mut Checkpoint,
+) -> anyhow::Result<CodeIds> {
+    let wasm = &neutron_inputs.wasm;
+    let code_ids = neutron_inputs.code_ids.as_ref();
+
+    let code_ids = CodeIds {
+        authorizations: resolve_code_id(
+            broadcaster,
+            "authorization",
+            wasm.authorizations.as_ref(),
+            code_ids.map(|ids| ids.authorizations),
+            checkpoint,
+        )
+        .await?,
+        processor: resolve_code_id(
+            broadcaster,
+            "processor",
+            wasm.processor.as_ref(),
+            code_ids.map(|ids| ids.processor),
+            checkpoint,
+        )
+        .await?,
+        cw20: resolve_code_id(
+            broadcaster,
+            "cw20",
+            wasm.cw20.as_ref(),
+            code_ids.map(|ids| ids.cw20),
+            checkpoint,
+        )
+        .await?,
+    };
+
+    println!("code ids: {code_ids:?}");
+
+    Ok(code_ids)
+}
+
+/// uploads the `source` wasm if it has a path, and otherwise uses the
+/// configured code id. the stored code must match the local wasm and the
+/// pinned checksum.
+async fn resolve_code_id(
+    broadcaster: &dyn Broadcaster,
+    contract: &str,
+    source: Option<&WasmSource>,
+    configured: Option<u64>,
+    checkpoint: &mut Checkpoint,
+) -> anyhow::Result<u64> {
+    let mut expected = match source {
+        Some(source) => source.checksum()?,
+        None => None,
+    };
+
+    let code_id = match source.and_then(|source| source.path.as_ref()) {
+        Some(path) => {
+            let wasm = fs::read(path)
+                .map_err(|e| anyhow::anyhow!("failed to read the {contract} wasm {path:?}: {e}"))?;
+            let checksum: [u8; 32] = Sha256::digest(&wasm).into();
+
+            if let Some(pinned) = expected {
+                anyhow::ensure!(
+                    checksum == pinned,
+                    "{contract} wasm {path:?} has checksum {}, pinned {}",
+                    hex::encode(checksum),
+                    hex::encode(pinned)
+                );
+            }
+            expected = Some(checksum);
+
+            // code stored by a previous run is reused only if it is still
+            // the local wasm
+            let mut stored = checkpoint.stored_code.get(contract).copied();
+            if let Some(code_id) = stored
+                && broadcaster.code_checksum(code_id).await? != checksum
+            {
+                stored = None;
+            }
+
+            match stored {
+                Some(code_id) => {
+                    println!("{contract} wasm already stored as code {code_id}, skipping");
+                    code_id
+                }
+                None => {
+                    println!(
+                        "uploading {contract} wasm {path:?} with checksum {}...",
+                        hex::encode(checksum)
+                    );
+                    let code_id = broadcaster.store_code(wasm).await?;
+                    println!("{contract} wasm stored as code {code_id}");
+
+                    checkpoint.stored_code.insert(contract.to_string(), code_id);
+                    checkpoint.save()?;
+                    code_id
+                }
+            }
+        }
+        None => configured
+            .ok_or_else(|| anyhow::anyhow!("no code id nor wasm path for the {contract}"))?,
+    };
+
+    // never instantiate code other than the one pinned
+    if let Some(expected) = expected {
+        let stored = broadcaster.code_checksum(code_id).await?;
+        anyhow::ensure!(
+            stored == expected,
+            "code {code_id} has checksum {}, expected {} for the {contract}",
+            hex::encode(stored),
+            hex::encode(expected)
+        );
+    }
+
+    Ok(code_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, path::PathBuf, sync::Mutex};
+
+    use async_trait::async_trait;
+    use cosmrs::proto::cosmwasm::wasm::v1::ContractInfo;
+    use cosmwasm_std::Binary;
+    use serde_json::Value;
+
+    use super::*;
+
+    /// chain storing code until `fail_after` uploads
+    struct Codes {
+        stored: Mutex<Vec<Vec<u8>>>,
+        fail_after: usize,
+    }
+
+    impl Codes {
+        fn new(fail_after: usize) -> Self {
+            Self {
+                stored: Mutex::new(Vec::new()),
+                fail_after,
+            }
+        }
+
+        fn uploads(&self) -> usize {
+            self.stored.lock().unwrap().len()
+        }
+    }
+
+    #[async_trait]
+    impl Broadcaster for Codes {
+        async fn sender(&self) -> anyhow::Result<String> {
+            Ok("neutron1deployer".to_string())
+        }
+
+        async fn predict_instantiate2_addr(
+            &self,
+            _code_id: u64,
+            _salt: &str,
+            _creator: &str,
+        ) -> anyhow::Result<String> {
+            unimplemented!()
+        }
+
+        async fn instantiate2(
+            &self,
+            _code_id: u64,
+            _label: &str,
+            _msg: Value,
+            _admin: Option<String>,
+            _salt: &str,
+        ) -> anyhow::Result<String> {
+            unimplemented!()
+        }
+
+        async fn contract_info(&self, _addr: &str) -> anyhow::Result<Option<ContractInfo>> {
+            unimplemented!()
+        }
+
+        async fn query(&self, _contract: &str, _msg: Value) -> anyhow::Result<Value> {
+            unimplemented!()
+        }
+
+        async fn execute(&self, _contract: &str, _msg: Value) -> anyhow::Result<()> {
+            unimplemented!()
+        }
+
+        async fn update_admin(&self, _contract: &str, _new_admin: &str) -> anyhow::Result<()> {
+            unimplemented!()
+        }
+
+        async fn store_code(&self, wasm: Vec<u8>) -> anyhow::Result<u64> {
+            let mut stored = self.stored.lock().unwrap();
+            anyhow::ensure!(stored.len() < self.fail_after, "out of gas");
+            stored.push(wasm);
+            Ok(stored.len() as u64)
+        }
+
+        async fn code_checksum(&self, code_id: u64) -> anyhow::Result<Vec<u8>> {
+            let stored = self.stored.lock().unwrap();
+            let wasm = stored
+                .get(code_id as usize - 1)
+                .ok_or_else(|| anyhow::anyhow!("no code {code_id}"))?;
+            Ok(Sha256::digest(wasm).to_vec())
+        }
+
+        async fn program_vk(&self, _app_id: &str) -> anyhow::Result<Binary> {
+            unimplemented!()
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("upload-{name}-{}", std::process::id()))
+    }
+
+    fn wasm_source(name: &str, wasm: &[u8]) -> WasmSource {
+        let path = temp_path(name);
+        fs::write(&path, wasm).unwrap();
+        WasmSource {
+            path: Some(path),
+            checksum: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_upload_keeps_previous_code() {
+        let checkpoint_path = temp_path("checkpoint");
+        let mut checkpoint = Checkpoint::load(&checkpoint_path, true).unwrap();
+        let authorization = wasm_source("authorization.wasm", b"authorization");
+        let processor = wasm_source("processor.wasm", b"processor");
+        let codes = Codes::new(1);
+
+        let code_id = resolve_code_id(
+            &codes,
+            "authorization",
+            Some(&authorization),
+            None,
+            &mut checkpoint,
+        )
+        .await
+        .unwrap();
+        assert_eq!(code_id, 1);
+
+        let err = resolve_code_id(&codes, "processor", Some(&processor), None, &mut checkpoint)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "out of gas");
+
+        // the resumed deployment stores the processor only
+        let mut checkpoint = Checkpoint::load(&checkpoint_path, false).unwrap();
+        assert_eq!(checkpoint.stored_code.get("authorization"), Some(&1));
+
+        let codes = Codes {
+            fail_after: 2,
+            ..codes
+        };
+        let code_id = resolve_code_id(
+            &codes,
+            "authorization",
+            Some(&authorization),
+            None,
+            &mut checkpoint,
+        )
+        .await
+        .unwrap();
+        assert_eq!(code_id, 1);
+        assert_eq!(codes.uploads(), 1);
+
+        let code_id = resolve_code_id(&codes, "processor", Some(&processor), None, &mut checkpoint)
+            .await
+            .unwrap();
+        assert_eq!(code_id, 2);
+
+        // a rebuilt wasm is stored again
+        let authorization = wasm_source("authorization.wasm", b"authorization v2");
+        let codes = Codes {
+            fail_after: 3,
+            ..codes
+        };
+        let code_id = resolve_code_id(
+            &codes,
+            "authorization",
+            Some(&authorization),
+            None,
+            &mut checkpoint,
+        )
+        .await
+        .unwrap();
+        assert_eq!(code_id, 3);
+
+        let _ = fs::remove_file(checkpoint_path);
+    }
+}
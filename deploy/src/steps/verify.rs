@@ -0,0 +1,285 @@
+use std::{fs, path::Path};
+
+use common::{NeutronStrategyConfig, REGULAR_MINT_CW20_LABEL};
+use cw20::{Cw20QueryMsg, MinterResponse};
+use serde::Serialize;
+use serde_json::{Value, json};
+use valence_authorization_utils::{authorization::AuthorizationModeInfo, msg::QueryMsg};
+
+use crate::steps::{Broadcaster, instantiate_contracts::verification_gateway};
+
+/// authorizations listed per query page
+const AUTHORIZATIONS_PAGE: u32 = 50;
+
+/// outcome of a single deployment check
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// pass/fail report of the deployment wiring
+#[derive(Debug, Serialize)]
+pub struct VerificationReport {
+    pub passed: bool,
+    pub checks: Vec<Check>,
+}
+
+impl VerificationReport {
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        println!("writing verification report to: {path:?}");
+
+        fs::write(path, toml::to_string(self)?)?;
+
+        Ok(())
+    }
+}
+
+/// queries every contract of the deployment and checks their wiring.
+/// failed queries fail their check rather than the verification.
+pub async fn verify_deployment(
+    broadcaster: &dyn Broadcaster,
+    cfg: &NeutronStrategyConfig,
+    gateway: &str,
+    regular_mode: &AuthorizationModeInfo,
+    zk_mode: &AuthorizationModeInfo,
+) -> VerificationReport {
+    let mut checks = vec![
+        check(
+            "processor authorization contract",
+            verify_processor(broadcaster, cfg).await,
+        ),
+        check(
+            "verification gateway",
+            verify_gateway(broadcaster, cfg, gateway).await,
+        ),
+        check("cw20 minter", verify_minter(broadcaster, cfg).await),
+    ];
+
+    checks.push(check(
+        "regular mint authorization",
+        verify_authorization(
+            broadcaster,
+            cfg,
+            false,
+            REGULAR_MINT_CW20_LABEL,
+            &json!(regular_mode),
+        )
+        .await
+        .map(|_| format!("{REGULAR_MINT_CW20_LABEL} exists")),
+    ));
+    checks.push(check(
+        "zk mint authorization",
+        verify_zk_authorization(broadcaster, cfg, &json!(zk_mode)).await,
+    ));
+
+    for check in &checks {
+        let status = if check.passed { "pass" } else { "FAIL" };
+        println!("[{status}] {}: {}", check.name, check.detail);
+    }
+
+    VerificationReport {
+        passed: checks.iter().all(|check| check.passed),
+        checks,
+    }
+}
+
+fn check(name: &str, result: anyhow::Result<String>) -> Check {
+    let (passed, detail) = match result {
+        Ok(detail) => (true, detail),
+        Err(e) => (false, e.to_string()),
+    };
+
+    Check {
+        name: name.to_string(),
+        passed,
+        detail,
+    }
+}
+
+async fn verify_processor(
+    broadcaster: &dyn Broadcaster,
+    cfg: &NeutronStrategyConfig,
+) -> anyhow::Result<String> {
+    let config = broadcaster
+        .query(
+            &cfg.processor,
+            json!(valence_processor_utils::msg::QueryMsg::Config {}),
+        )
+        .await?;
+    let authorization = config["authorization_contract"]
+        .as_str()
+        .unwrap_or_default();
+
+    anyhow::ensure!(
+        authorization == cfg.authorizations,
+        "processor points at {authorization:?}, expected {}",
+        cfg.authorizations
+    );
+
+    Ok(format!("processor points at {authorization}"))
+}
+
+async fn verify_gateway(
+    broadcaster: &dyn Broadcaster,
+    cfg: &NeutronStrategyConfig,
+    expected: &str,
+) -> anyhow::Result<String> {
+    let gateway = verification_gateway(broadcaster, &cfg.authorizations)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("verification gateway not set"))?;
+
+    anyhow::ensure!(
+        gateway == expected,
+        "verification gateway is {gateway}, expected {expected}"
+    );
+
+    Ok(format!("verification gateway {gateway}"))
+}
+
+async fn verify_minter(
+    broadcaster: &dyn Broadcaster,
+    cfg: &NeutronStrategyConfig,
+) -> anyhow::Result<String> {
+    let minter = broadcaster
+        .query(&cfg.cw20, json!(Cw20QueryMsg::Minter {}))
+        .await?;
+    let minter: Option<MinterResponse> = serde_json::from_value(minter)?;
+    let minter = minter.ok_or_else(|| anyhow::anyhow!("cw20 has no minter"))?;
+
+    anyhow::ensure!(
+        minter.minter == cfg.processor,
+        "cw20 minter is {}, expected the processor {}",
+        minter.minter,
+        cfg.processor
+    );
+
+    Ok(format!("cw20 minted by {}", minter.minter))
+}
+
+async fn verify_zk_authorization(
+    broadcaster: &dyn Broadcaster,
+    cfg: &NeutronStrategyConfig,
+    expected_mode: &Value,
+) -> anyhow::Result<String> {
+    let label = &cfg.zk_authorization_label;
+    let authorization = verify_authorization(broadcaster, cfg, true, label, expected_mode).await?;
+
+    let vk = broadcaster.program_vk(&cfg.coprocessor_app_id).await?;
+    anyhow::ensure!(
+        authorization["vk"] == json!(vk),
+        "{label} vk {} differs from the vk {vk} of app {}",
+        authorization["vk"],
+        cfg.coprocessor_app_id
+    );
+
+    Ok(format!(
+        "{label} exists with the vk of app {}",
+        cfg.coprocessor_app_id
+    ))
+}
+
+/// finds the `label` authorization, zk or regular, checking its mode
+async fn verify_authorization(
+    broadcaster: &dyn Broadcaster,
+    cfg: &NeutronStrategyConfig,
+    zk: bool,
+    label: &str,
+    expected_mode: &Value,
+) -> anyhow::Result<Value> {
+    let authorization = find_authorization(broadcaster, &cfg.authorizations, zk, label)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("no {label} authorization"))?;
+
+    let mode = mode_kind(&authorization["mode"]);
+    let expected = mode_kind(expected_mode);
+    anyhow::ensure!(
+        mode == expected,
+        "{label} has mode {mode}, expected {expected}"
+    );
+
+    Ok(authorization)
+}
+
+/// pages through the zk, or regular, authorizations of the `authorizations`
+/// contract until the `label` one
+pub async fn find_authorization(
+    broadcaster: &dyn Broadcaster,
+    authorizations: &str,
+    zk: bool,
+    label: &str,
+) -> anyhow::Result<Option<Value>> {
+    let mut start_after = None;
+
+    loop {
+        let limit = Some(AUTHORIZATIONS_PAGE);
+        let query = if zk {
+            QueryMsg::ZkAuthorizations { start_after, limit }
+        } else {
+            QueryMsg::Authorizations { start_after, limit }
+        };
+        let page = broadcaster.query(authorizations, json!(query)).await?;
+        let page: Vec<Value> = serde_json::from_value(page)?;
+
+        if let Some(found) = page
+            .iter()
+            .find(|authorization| authorization["label"] == label)
+        {
+            return Ok(Some(found.clone()));
+        }
+
+        // a short page is the last one
+        let last = page
+            .last()
+            .and_then(|authorization| authorization["label"].as_str());
+        match last {
+            Some(last) if page.len() == AUTHORIZATIONS_PAGE as usize => {
+                start_after = Some(last.to_string());
+            }
+            _ => return Ok(None),
+        }
+    }
+}
+
+/// variant path of an authorization mode, e.g. `permissioned/without_call_limit`.
+/// the contract replaces the permissioned addresses with a token, so only
+/// the variants are compared.
+fn mode_kind(mode: &Value) -> String {
+    match mode {
+        Value::String(variant) => variant.clone(),
+        Value::Object(variants) => variants
+            .iter()
+            .next()
+            .map(|(variant, inner)| match inner {
+                Value::Object(_) => format!("{variant}/{}", mode_kind(inner)),
+                _ => variant.clone(),
+            })
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use valence_authorization_utils::authorization::PermissionTypeInfo;
+
+    use super::*;
+
+    #[test]
+    fn test_mode_kind() {
+        let expected = json!(AuthorizationModeInfo::Permissioned(
+            PermissionTypeInfo::WithoutCallLimit(vec!["neutron1deployer".to_string()])
+        ));
+        let on_chain = json!({
+            "permissioned": { "without_call_limit": "factory/neutron1authorization/1" }
+        });
+
+        assert_eq!(mode_kind(&expected), "permissioned/without_call_limit");
+        assert_eq!(mode_kind(&on_chain), mode_kind(&expected));
+        assert_eq!(
+            mode_kind(&json!(AuthorizationModeInfo::Permissionless)),
+            "permissionless"
+        );
+    }
+}
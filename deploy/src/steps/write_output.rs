@@ -1,21 +1,49 @@
-use std::{fs, path::PathBuf};
+use std::{fs, path::Path};
 
 use common::NeutronStrategyConfig;
-use common::OUTPUTS_DIR;
 
 pub fn write_setup_artifacts(
-    cd: PathBuf,
-    neutron_cfg: NeutronStrategyConfig,
+    path: &Path,
+    neutron_cfg: &NeutronStrategyConfig,
 ) -> anyhow::Result<()> {
     println!("writing outputs...");
 
     // Save the Neutron Strategy Config to a toml file
-    let neutron_cfg_toml = toml::to_string(&neutron_cfg)?;
+    let neutron_cfg_toml = toml::to_string(neutron_cfg)?;
 
-    let target_path = cd.join(format!("{OUTPUTS_DIR}/neutron_strategy_config.toml"));
-    println!("writing neutron_strategy_config.toml to: {target_path:?}");
+    println!("writing neutron strategy config to: {path:?}");
 
-    fs::write(target_path, neutron_cfg_toml)?;
+    fs::write(path, neutron_cfg_toml)?;
 
     Ok(())
 }
+
+/// reads back the strategy config written by an earlier step
+pub fn read_setup_artifacts(path: &Path) -> anyhow::Result<NeutronStrategyConfig> {
+    let parameters = fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("failed to read the strategy config {path:?}: {e}"))?;
+
+    Ok(toml::from_str(&parameters)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use common::ZK_MINT_CW20_LABEL;
+
+    use super::*;
+
+    #[test]
+    fn test_read_setup_artifacts_predating_params_hash() {
+        // written before the config recorded the params hash, erc20 and holder
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../artifacts/neutron_strategy_config.toml");
+
+        let cfg = read_setup_artifacts(&path).unwrap();
+
+        assert_eq!(cfg.circuit_params_hash, None);
+        assert!(cfg.erc20.is_empty() && cfg.holder.is_empty());
+        assert_eq!(cfg.zk_authorization_label, ZK_MINT_CW20_LABEL);
+    }
+}
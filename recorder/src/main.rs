@@ -0,0 +1,434 @@
+// Storage proof fixture recorder and proof request previewer
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use alloy_primitives::{Address, B256, U256};
+use alloy_rpc_types_eth::EIP1186AccountProofResponse;
+use clap::Parser;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use storage_proof_controller::host::MemoryHost;
+use storage_proof_core::{
+    fixture::{Fixture, FixtureBlock, FIXTURE_VERSION},
+    network::NetworkProfile,
+    proof::mapping_slot_key,
+    ControllerInputs,
+};
+use synth::{SynthState, EIP1967_IMPLEMENTATION_SLOT};
+
+mod preview;
+mod synth;
+
+/// records storage proof fixtures
+#[derive(Debug, Parser)]
+enum Command {
+    /// records the `eth_getProof` response of one or more erc20 balance
+    /// holders at the latest block the coprocessor validated for the domain
+    Record(RecordArgs),
+    /// builds the fixture over a synthetic state instead, for cases no
+    /// mainnet recording covers. the description says so.
+    Synth(SynthArgs),
+    /// dry-runs a proof request: fetches the proof the way `record` does
+    /// and runs the controller and circuit logic on it natively, printing
+    /// the zk message the circuit would commit to
+    Preview(PreviewArgs),
+}
+
+/// fixture contents shared by the commands
+#[derive(Debug, clap::Args)]
+struct FixtureArgs {
+    /// erc20 contract to prove the balances of
+    #[arg(long)]
+    erc20: String,
+
+    /// balance holder. repeat the flag to record a multi-slot proof
+    #[arg(long = "holder", required = true)]
+    holders: Vec<String>,
+
+    /// neutron address recorded as the mint recipient
+    #[arg(long)]
+    neutron_addr: String,
+
+    /// erc20 balances mapping slot index
+    #[arg(long, default_value_t = 9)]
+    slot_index: u64,
+
+    /// network profile the alchemy network and coprocessor domain default to
+    #[arg(long, default_value = "mainnet")]
+    profile: String,
+
+    /// alchemy network the controller requests the proof on
+    #[arg(long)]
+    network: Option<String>,
+
+    /// coprocessor domain the block belongs to
+    #[arg(long)]
+    domain: Option<String>,
+
+    /// what the fixture covers. generated from the arguments if omitted
+    #[arg(long)]
+    description: Option<String>,
+}
+
+/// services the proof is fetched from
+#[derive(Debug, clap::Args)]
+struct Endpoints {
+    /// ethereum json-rpc endpoint, e.g. an alchemy url or a local archive node
+    #[arg(long, env = "ETH_RPC_URL")]
+    rpc_url: String,
+
+    /// coprocessor service the domain block and root are taken from
+    #[arg(
+        long,
+        env = "COPROCESSOR_URL",
+        default_value = "https://service.coprocessor.valence.zone"
+    )]
+    coprocessor: String,
+}
+
+#[derive(Debug, clap::Args)]
+struct RecordArgs {
+    #[command(flatten)]
+    fixture: FixtureArgs,
+
+    #[command(flatten)]
+    endpoints: Endpoints,
+
+    /// fixture file to write
+    #[arg(long)]
+    out: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+struct PreviewArgs {
+    #[command(flatten)]
+    fixture: FixtureArgs,
+
+    #[command(flatten)]
+    endpoints: Endpoints,
+}
+
+#[derive(Debug, clap::Args)]
+struct SynthArgs {
+    #[command(flatten)]
+    fixture: FixtureArgs,
+
+    /// fixture file to write
+    #[arg(long)]
+    out: PathBuf,
+
+    /// balance of each holder, in base units. zero balances are absent
+    /// from the storage trie, as on mainnet
+    #[arg(long = "balance", required = true)]
+    balances: Vec<U256>,
+
+    /// makes the token an eip-1967 proxy of this implementation
+    #[arg(long)]
+    implementation: Option<String>,
+
+    /// block number recorded in the fixture
+    #[arg(long, default_value_t = 0)]
+    block: u64,
+}
+
+/// latest block validated by the coprocessor for a domain
+#[derive(Debug, Deserialize)]
+struct ValidatedBlock {
+    number: u64,
+    root: B256,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    match Command::parse() {
+        Command::Record(args) => {
+            let fixture = fetch(args.fixture, &args.endpoints).await?;
+            write_fixture(&fixture, &args.out)
+        }
+        Command::Synth(args) => synthesize(args),
+        Command::Preview(args) => preview(args).await,
+    }
+}
+
+async fn preview(args: PreviewArgs) -> anyhow::Result<()> {
+    let fixture = fetch(args.fixture, &args.endpoints).await?;
+
+    // the controller runs against the fetched block and proof, so nothing
+    // is requested from, or written to, the coprocessor
+    let host = MemoryHost::from_fixture(&fixture)?;
+    let preview = preview::preview_with(&host, &fixture.inputs)?;
+
+    println!("{}", serde_json::to_string_pretty(&preview)?);
+
+    Ok(())
+}
+
+/// fetches the proof of the holders at the latest domain block
+async fn fetch(args: FixtureArgs, endpoints: &Endpoints) -> anyhow::Result<Fixture> {
+    let client = reqwest::Client::new();
+    let (network, domain) = network_and_domain(&args)?;
+    let erc20 = Address::from_str(&args.erc20)?;
+
+    // the circuit opens the proof against the root the coprocessor
+    // validated for the domain, so the block and root are taken from it
+    // rather than from the ethereum node
+    let url = format!(
+        "{}/api/registry/domain/{domain}/latest",
+        endpoints.coprocessor
+    );
+    let block: ValidatedBlock = client
+        .get(&url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .map_err(|e| anyhow::anyhow!("unexpected latest block of domain {domain}: {e}"))?;
+    let block_tag = format!("{:#x}", block.number);
+
+    println!(
+        "recording at domain block {}, root {}",
+        block.number, block.root
+    );
+
+    let proof = rpc(
+        &client,
+        &endpoints.rpc_url,
+        "eth_getProof",
+        json!([erc20, slot_keys(&args)?, block_tag]),
+    )
+    .await?;
+    let proof: EIP1186AccountProofResponse = serde_json::from_value(proof)?;
+
+    Ok(fixture(
+        args,
+        network,
+        domain,
+        block.number,
+        block.root,
+        proof,
+    ))
+}
+
+fn synthesize(args: SynthArgs) -> anyhow::Result<()> {
+    let (network, domain) = network_and_domain(&args.fixture)?;
+    let erc20 = Address::from_str(&args.fixture.erc20)?;
+
+    anyhow::ensure!(
+        args.balances.len() == args.fixture.holders.len(),
+        "expected one balance per holder"
+    );
+
+    let keys = slot_keys(&args.fixture)?;
+    let mut storage: BTreeMap<B256, U256> = keys.iter().copied().zip(args.balances).collect();
+    if let Some(implementation) = &args.implementation {
+        let implementation = Address::from_str(implementation)?;
+        storage.insert(
+            EIP1967_IMPLEMENTATION_SLOT,
+            U256::from_be_slice(implementation.as_slice()),
+        );
+    }
+
+    let (root, proof) = SynthState::with_token(erc20, storage).get_proof(erc20, &keys)?;
+
+    let mut fixture_args = args.fixture;
+    let description = fixture_args
+        .description
+        .take()
+        .unwrap_or_else(|| describe(&fixture_args));
+    fixture_args.description = Some(format!(
+        "{description}. Synthetic state built by `recorder synth`, not a mainnet recording."
+    ));
+
+    let fixture = fixture(fixture_args, network, domain, args.block, root, proof);
+
+    write_fixture(&fixture, &args.out)
+}
+
+fn network_and_domain(args: &FixtureArgs) -> anyhow::Result<(String, String)> {
+    let profile = NetworkProfile::by_name(&args.profile)
+        .ok_or_else(|| anyhow::anyhow!("unknown network profile {}", args.profile))?;
+    let network = args
+        .network
+        .clone()
+        .or(profile.alchemy_network.map(String::from))
+        .ok_or_else(|| anyhow::anyhow!("network {} has no alchemy network", profile.name))?;
+    let domain = args
+        .domain
+        .clone()
+        .or(profile.coprocessor_domain.map(String::from))
+        .ok_or_else(|| anyhow::anyhow!("network {} has no coprocessor domain", profile.name))?;
+
+    Ok((network, domain))
+}
+
+/// balances mapping storage keys of the holders
+fn slot_keys(args: &FixtureArgs) -> anyhow::Result<Vec<B256>> {
+    args.holders
+        .iter()
+        .map(|holder| {
+            Ok(mapping_slot_key(
+                Address::from_str(holder)?,
+                args.slot_index,
+            ))
+        })
+        .collect()
+}
+
+fn describe(args: &FixtureArgs) -> String {
+    format!(
+        "{} balances of {} at slot {}",
+        args.erc20,
+        args.holders.join(", "),
+        args.slot_index
+    )
+}
+
+fn fixture(
+    args: FixtureArgs,
+    network: String,
+    domain: String,
+    number: u64,
+    root: B256,
+    proof: EIP1186AccountProofResponse,
+) -> Fixture {
+    for storage_proof in &proof.storage_proof {
+        println!(
+            "storage key {:#x} = {}",
+            storage_proof.key.as_b256(),
+            storage_proof.value
+        );
+    }
+
+    let description = args.description.clone().unwrap_or_else(|| describe(&args));
+
+    Fixture {
+        version: FIXTURE_VERSION,
+        description,
+        network,
+        domain,
+        block: FixtureBlock { number, root },
+        inputs: ControllerInputs {
+            erc20: args.erc20,
+            eth_addr: args.holders[0].clone(),
+            neutron_addr: args.neutron_addr,
+            request: None,
+        },
+        slot_index: args.slot_index,
+        proof,
+    }
+}
+
+fn write_fixture(fixture: &Fixture, out: &Path) -> anyhow::Result<()> {
+    let mut contents = serde_json::to_string_pretty(fixture)?;
+    contents.push('\n');
+    fs::write(out, contents)?;
+
+    println!("fixture written to {out:?}");
+
+    Ok(())
+}
+
+/// performs a json-rpc call, returning its `result`
+async fn rpc(
+    client: &reqwest::Client,
+    url: &str,
+    method: &str,
+    params: Value,
+) -> anyhow::Result<Value> {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    });
+
+    let response: Value = client
+        .post(url)
+        .json(&request)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    if let Some(error) = response.get("error") {
+        anyhow::bail!("{method} failed: {error}");
+    }
+
+    response
+        .get("result")
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("{method} returned no result"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOLDER: &str = "0x28C6c06298d514Db089934071355E5743bf21d60";
+
+    #[derive(Debug, Parser)]
+    struct Args {
+        #[command(flatten)]
+        fixture: FixtureArgs,
+    }
+
+    fn args(holders: &[&str]) -> FixtureArgs {
+        let args = [
+            "recorder",
+            "--erc20",
+            "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+            "--neutron-addr",
+            "neutron1recipient",
+        ];
+        let holders = holders.iter().flat_map(|holder| ["--holder", *holder]);
+
+        Args::parse_from(args.into_iter().chain(holders)).fixture
+    }
+
+    #[test]
+    fn test_slot_keys() {
+        let mut args = args(&[HOLDER, "0x0000000000000000000000000000000000000001"]);
+
+        let keys = slot_keys(&args).unwrap();
+        assert_eq!(
+            keys,
+            [
+                mapping_slot_key(Address::from_str(HOLDER).unwrap(), 9),
+                mapping_slot_key(Address::with_last_byte(1), 9),
+            ]
+        );
+
+        args.slot_index = 0;
+        assert_ne!(slot_keys(&args).unwrap(), keys);
+
+        args.holders.push("not an address".into());
+        assert!(slot_keys(&args).is_err());
+    }
+
+    #[test]
+    fn test_network_and_domain() {
+        let mut args = args(&[HOLDER]);
+        let profile = NetworkProfile::by_name("mainnet").unwrap();
+
+        let (network, domain) = network_and_domain(&args).unwrap();
+        assert_eq!(Some(network.as_str()), profile.alchemy_network);
+        assert_eq!(Some(domain.as_str()), profile.coprocessor_domain);
+
+        // explicit values win over the profile
+        args.network = Some("eth-sepolia".into());
+        args.domain = Some("ethereum-sepolia".into());
+        assert_eq!(
+            network_and_domain(&args).unwrap(),
+            ("eth-sepolia".into(), "ethereum-sepolia".into())
+        );
+
+        args.profile = "unknown".into();
+        assert!(network_and_domain(&args).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+// Native dry-run of a proof request
+use alloy_primitives::hex;
+use serde_json::{json, Value};
+use storage_proof_controller::host::ControllerHost;
+use storage_proof_core::ControllerInputs;
+
+/// dry-runs a proof request: computes the witnesses and runs the circuit
+/// logic on the host, returning a preview of the zk message that would
+/// be proven. nothing is written to the virtual filesystem.
+pub fn preview_with<H: ControllerHost>(
+    host: &H,
+    witness_inputs: &ControllerInputs,
+) -> anyhow::Result<Value> {
+    let prepared = storage_proof_controller::prepare_witnesses(host, witness_inputs)?;
+
+    let statement = storage_proof_circuit::verify_witnesses(&prepared.witnesses)?;
+    let zk_msg = storage_proof_circuit::build_zk_msg(&statement);
+
+    Ok(json!({
+        "recipient": statement.recipient,
+        "amount": statement.amount.to_string(),
+        "block": statement.block_number,
+        "root": prepared.entry.root,
+        "zk_message": zk_msg,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::U256;
+    use storage_proof_controller::{archive::ARCHIVE_INDEX_PATH, host::MemoryHost};
+    use storage_proof_core::fixture::Fixture;
+
+    use super::*;
+
+    const USDC_FIXTURE: &[u8] =
+        include_bytes!("../../circuits/storage_proof/core/fixtures/usdc.json");
+
+    fn fixture() -> Fixture {
+        Fixture::from_slice(USDC_FIXTURE).unwrap()
+    }
+
+    #[test]
+    fn test_preview() {
+        let fixture = fixture();
+        let host = MemoryHost::from_fixture(&fixture).unwrap();
+
+        let preview = preview_with(&host, &fixture.inputs).unwrap();
+
+        assert_eq!(preview["recipient"], fixture.inputs.neutron_addr);
+        assert_eq!(preview["amount"], "7191271");
+        assert_eq!(preview["block"], fixture.block.number);
+        assert_eq!(preview["root"], hex::encode_prefixed(fixture.block.root));
+        assert_eq!(preview["zk_message"]["registry"], 0);
+
+        // dry runs must not index the request
+        assert!(host.file(ARCHIVE_INDEX_PATH).is_none());
+    }
+
+    #[test]
+    fn test_preview_rejects_tampered_proof() {
+        let mut fixture = fixture();
+        fixture.proof.storage_proof[0].value += U256::from(1);
+        let host = MemoryHost::from_fixture(&fixture).unwrap();
+
+        assert!(preview_with(&host, &fixture.inputs).is_err());
+    }
+}
@@ -0,0 +1,261 @@
+// Synthetic state for fixtures no mainnet recording covers yet
+use std::collections::BTreeMap;
+
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
+use alloy_rpc_types_eth::{EIP1186AccountProofResponse, EIP1186StorageProof};
+use alloy_trie::{
+    proof::ProofRetainer, HashBuilder, Nibbles, TrieAccount, EMPTY_ROOT_HASH, KECCAK_EMPTY,
+};
+
+/// eip-1967 implementation slot, `keccak256("eip1967.proxy.implementation") - 1`
+pub const EIP1967_IMPLEMENTATION_SLOT: B256 = B256::new([
+    0x36, 0x08, 0x94, 0xa1, 0x3b, 0xa1, 0xa3, 0x21, 0x06, 0x67, 0xc8, 0x28, 0x49, 0x2d, 0xb9, 0x8d,
+    0xca, 0x3e, 0x20, 0x76, 0xcc, 0x37, 0x35, 0xa9, 0x20, 0xa3, 0xca, 0x50, 0x5d, 0x38, 0x2b, 0xbc,
+]);
+
+/// number of filler accounts and storage slots, so the proofs go through
+/// branch nodes like mainnet ones do
+const FILLER: u64 = 64;
+
+/// account of the synthetic state
+#[derive(Debug, Clone, Default)]
+pub struct SynthAccount {
+    pub nonce: u64,
+    pub balance: U256,
+    pub code_hash: B256,
+    /// storage slots; zero values are absent from the trie
+    pub storage: BTreeMap<B256, U256>,
+}
+
+/// world state made of a handful of accounts, answering `eth_getProof`
+/// the way an ethereum node does
+#[derive(Debug, Clone, Default)]
+pub struct SynthState {
+    pub accounts: BTreeMap<Address, SynthAccount>,
+}
+
+impl SynthState {
+    /// state holding the erc20 `token` with the given storage, next to
+    /// filler accounts. the token storage is padded with filler slots.
+    pub fn with_token(token: Address, mut storage: BTreeMap<B256, U256>) -> Self {
+        for i in 0..FILLER {
+            let slot = keccak256(format!("filler slot {i}"));
+            storage.entry(slot).or_insert(U256::from(i + 1));
+        }
+
+        let mut accounts = BTreeMap::new();
+        for i in 0..FILLER {
+            let address = Address::from_slice(&keccak256(format!("filler account {i}"))[12..]);
+            accounts.insert(
+                address,
+                SynthAccount {
+                    nonce: i,
+                    balance: U256::from(i * 1_000_000_007),
+                    code_hash: KECCAK_EMPTY,
+                    storage: BTreeMap::new(),
+                },
+            );
+        }
+
+        accounts.insert(
+            token,
+            SynthAccount {
+                nonce: 1,
+                balance: U256::ZERO,
+                code_hash: keccak256(format!("code of {token}")),
+                storage,
+            },
+        );
+
+        Self { accounts }
+    }
+
+    /// state root and `eth_getProof` response of `address` for the storage `keys`
+    pub fn get_proof(
+        &self,
+        address: Address,
+        keys: &[B256],
+    ) -> anyhow::Result<(B256, EIP1186AccountProofResponse)> {
+        let account = self
+            .accounts
+            .get(&address)
+            .ok_or_else(|| anyhow::anyhow!("account {address} is not in the synthetic state"))?;
+
+        let storage_leaves = account
+            .storage
+            .iter()
+            .filter(|(_, value)| !value.is_zero())
+            .map(|(slot, value)| (keccak256(slot), alloy_rlp::encode(value)))
+            .collect();
+        let storage_targets: Vec<B256> = keys.iter().map(keccak256).collect();
+        let (storage_hash, storage_proofs) = trie(storage_leaves, &storage_targets);
+
+        let account_leaves = self
+            .accounts
+            .iter()
+            .map(|(address, account)| {
+                let trie_account = TrieAccount {
+                    nonce: account.nonce,
+                    balance: account.balance,
+                    storage_root: storage_root(account),
+                    code_hash: account.code_hash,
+                };
+                (keccak256(address), alloy_rlp::encode(trie_account))
+            })
+            .collect();
+        let (state_root, mut account_proofs) = trie(account_leaves, &[keccak256(address)]);
+
+        let storage_proof = keys
+            .iter()
+            .zip(storage_proofs)
+            .map(|(key, proof)| EIP1186StorageProof {
+                key: (*key).into(),
+                value: account.storage.get(key).copied().unwrap_or_default(),
+                proof,
+            })
+            .collect();
+
+        let proof = EIP1186AccountProofResponse {
+            address,
+            balance: account.balance,
+            code_hash: account.code_hash,
+            nonce: account.nonce,
+            storage_hash,
+            account_proof: account_proofs.remove(0),
+            storage_proof,
+        };
+
+        Ok((state_root, proof))
+    }
+}
+
+fn storage_root(account: &SynthAccount) -> B256 {
+    let leaves: BTreeMap<_, _> = account
+        .storage
+        .iter()
+        .filter(|(_, value)| !value.is_zero())
+        .map(|(slot, value)| (keccak256(slot), alloy_rlp::encode(value)))
+        .collect();
+
+    if leaves.is_empty() {
+        return EMPTY_ROOT_HASH;
+    }
+
+    trie(leaves, &[]).0
+}
+
+/// root of the trie of the hashed-key `leaves`, and the proof of each of
+/// the hashed `targets`, present in the trie or not
+fn trie(leaves: BTreeMap<B256, Vec<u8>>, targets: &[B256]) -> (B256, Vec<Vec<Bytes>>) {
+    let targets: Vec<Nibbles> = targets.iter().map(Nibbles::unpack).collect();
+    let mut builder =
+        HashBuilder::default().with_proof_retainer(ProofRetainer::new(targets.clone()));
+
+    // the builder expects the leaves in key order, which the map provides
+    for (key, value) in &leaves {
+        builder.add_leaf(Nibbles::unpack(key), value);
+    }
+
+    let root = builder.root();
+    let nodes = builder.take_proof_nodes();
+
+    let proofs = targets
+        .iter()
+        .map(|target| {
+            nodes
+                .matching_nodes_sorted(target)
+                .into_iter()
+                .map(|(_, node)| node)
+                .collect()
+        })
+        .collect();
+
+    (root, proofs)
+}
+
+#[cfg(test)]
+mod tests {
+    use storage_proof_core::proof::{mapping_slot_key, verify_proof};
+
+    use super::*;
+
+    const TOKEN: Address = Address::new([0x11; 20]);
+
+    fn holder(byte: u8) -> Address {
+        Address::new([byte; 20])
+    }
+
+    /// the single-key responses a multi-key one is made of
+    fn split(proof: &EIP1186AccountProofResponse) -> Vec<EIP1186AccountProofResponse> {
+        proof
+            .storage_proof
+            .iter()
+            .map(|storage_proof| EIP1186AccountProofResponse {
+                storage_proof: vec![storage_proof.clone()],
+                ..proof.clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_inclusion_proof() {
+        let key = mapping_slot_key(holder(0xaa), 9);
+        let storage = BTreeMap::from([(key, U256::from(1_000_000))]);
+
+        let (root, mut proof) = SynthState::with_token(TOKEN, storage)
+            .get_proof(TOKEN, &[key])
+            .unwrap();
+        assert_eq!(proof.storage_proof[0].value, U256::from(1_000_000));
+
+        verify_proof(&proof, root).unwrap();
+
+        proof.storage_proof[0].value = U256::from(1_000_001);
+        assert!(verify_proof(&proof, root).is_err());
+    }
+
+    #[test]
+    fn test_exclusion_proof() {
+        let key = mapping_slot_key(holder(0xaa), 9);
+        // a zero balance is left out of the trie, as an absent one is
+        let storage = BTreeMap::from([(key, U256::ZERO)]);
+
+        let (root, mut proof) = SynthState::with_token(TOKEN, storage)
+            .get_proof(TOKEN, &[key])
+            .unwrap();
+        assert!(proof.storage_proof[0].value.is_zero());
+
+        verify_proof(&proof, root).unwrap();
+
+        proof.storage_proof[0].value = U256::from(1);
+        assert!(verify_proof(&proof, root).is_err());
+    }
+
+    #[test]
+    fn test_multi_key_proof() {
+        let keys = [
+            mapping_slot_key(holder(0xaa), 9),
+            mapping_slot_key(holder(0xbb), 9),
+            mapping_slot_key(holder(0xcc), 9),
+        ];
+        let storage = BTreeMap::from([(keys[0], U256::from(5)), (keys[1], U256::from(7))]);
+
+        let (root, proof) = SynthState::with_token(TOKEN, storage)
+            .get_proof(TOKEN, &keys)
+            .unwrap();
+
+        let values: Vec<U256> = proof.storage_proof.iter().map(|p| p.value).collect();
+        assert_eq!(values, [U256::from(5), U256::from(7), U256::ZERO]);
+
+        for (key, single) in keys.iter().zip(split(&proof)) {
+            assert_eq!(single.storage_proof[0].key.as_b256(), *key);
+            verify_proof(&single, root).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_proof_of_unknown_account() {
+        let state = SynthState::with_token(TOKEN, BTreeMap::new());
+
+        assert!(state.get_proof(holder(0xaa), &[]).is_err());
+    }
+}
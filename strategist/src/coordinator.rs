@@ -1,7 +1,12 @@
 use async_trait::async_trait;
-use common::ZK_MINT_CW20_LABEL;
-use cw20::{BalanceResponse, Cw20QueryMsg};
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg};
 use log::info;
+use storage_proof_core::output::split_public_inputs;
+use valence_authorization_utils::{
+    authorization::{AuthorizationMsg, Subroutine},
+    msg::ProcessorMessage,
+    zk_authorization::ZkMessage,
+};
 use valence_coordinator_sdk::coordinator::ValenceCoordinator;
 use valence_domain_clients::{
     coprocessor::base_client::{Base64, CoprocessorBaseClient, Proof},
@@ -11,8 +16,6 @@ use valence_domain_clients::{
 use crate::strategy::Strategy;
 
 const COORDINATOR_LOG_TARGET: &str = "COORDINATOR";
-const USDC_ERC20_ADDR: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
-const SRC_ETH_ADDR: &str = "0x8d41bb082C6050893d1eC113A104cc4C087F2a2a";
 
 // implement the ValenceCoordinator trait for the Strategy struct.
 // This trait defines the main loop of the strategy and inherits
@@ -34,9 +37,10 @@ impl ValenceCoordinator for Strategy {
             .to_string();
 
         let circuit_inputs = storage_proof_core::ControllerInputs {
-            erc20: USDC_ERC20_ADDR.to_string(),
-            eth_addr: SRC_ETH_ADDR.to_string(),
+            erc20: self.neutron_cfg.erc20.clone(),
+            eth_addr: self.neutron_cfg.holder.clone(),
             neutron_addr: ntrn_addr.to_string(),
+            request: None,
         };
 
         let proof_request = serde_json::to_value(circuit_inputs)?;
@@ -54,6 +58,11 @@ impl ValenceCoordinator for Strategy {
         let program_proof = decode(resp.program)?;
         let domain_proof = decode(resp.domain)?;
 
+        // refuse to post proofs minting anything else than our balance on
+        // our cw20
+        let amount = check_zk_msg(&program_proof.1, &self.neutron_cfg.cw20, &ntrn_addr)?;
+        info!(target: COORDINATOR_LOG_TARGET, "proof mints {amount} to {ntrn_addr}");
+
         let cw20_bal_query = Cw20QueryMsg::Balance {
             address: ntrn_addr.to_string(),
         };
@@ -69,7 +78,7 @@ impl ValenceCoordinator for Strategy {
         valence_coordinator_sdk::core::cw::post_zkp_on_chain(
             &self.neutron_client,
             &self.neutron_cfg.authorizations,
-            ZK_MINT_CW20_LABEL,
+            &self.neutron_cfg.zk_authorization_label,
             program_proof,
             domain_proof,
         )
@@ -96,3 +105,98 @@ fn decode(a: Proof) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
 
     Ok((proof, inputs))
 }
+
+/// decodes the zk message of the program proof public inputs the way the
+/// authorization contract does, and checks it mints on `cw20` to
+/// `recipient`. returns the minted amount.
+fn check_zk_msg(inputs: &[u8], cw20: &str, recipient: &str) -> anyhow::Result<u128> {
+    let (_root, output) = split_public_inputs(inputs)?;
+    let zk_msg: ZkMessage = cosmwasm_std::from_json(output)?;
+
+    let AuthorizationMsg::EnqueueMsgs {
+        msgs, subroutine, ..
+    } = zk_msg.message
+    else {
+        anyhow::bail!("zk message does not enqueue messages");
+    };
+    let Subroutine::Atomic(subroutine) = subroutine else {
+        anyhow::bail!("zk message does not enqueue an atomic subroutine");
+    };
+    let ([ProcessorMessage::CosmwasmExecuteMsg { msg }], [function]) =
+        (msgs.as_slice(), subroutine.functions.as_slice())
+    else {
+        anyhow::bail!("zk message must execute a single cosmwasm message");
+    };
+
+    anyhow::ensure!(
+        serde_json::to_value(&function.contract_address)?
+            == serde_json::json!({ "|library_account_addr|": cw20 }),
+        "zk message does not execute on the cw20 {cw20}"
+    );
+
+    let Cw20ExecuteMsg::Mint {
+        recipient: minted_to,
+        amount,
+    } = cosmwasm_std::from_json(msg)?
+    else {
+        anyhow::bail!("zk message does not mint");
+    };
+    anyhow::ensure!(
+        minted_to == recipient,
+        "zk message mints to {minted_to}, expected {recipient}"
+    );
+
+    Ok(amount.u128())
+}
+
+#[cfg(test)]
+mod tests {
+    use storage_proof_core::output::COPROCESSOR_ROOT_LEN;
+
+    use super::*;
+
+    const GOLDEN_USDC: &[u8] =
+        include_bytes!("../../circuits/storage_proof/circuit/golden/usdc.json");
+    const CW20: &str = "neutron15s4cwrsqu6np228f759kh5az7dups6wyyanldadhd52z9ltyvpkq00sy2p";
+    const RECIPIENT: &str = "neutron1m6w8n0hluq7avn40hj0n6jnj8ejhykfrwfnnjh";
+
+    /// public inputs of a proof of the golden usdc circuit output
+    fn inputs(output: &[u8]) -> Vec<u8> {
+        let mut inputs = vec![1u8; COPROCESSOR_ROOT_LEN];
+        inputs.extend_from_slice(output);
+        inputs
+    }
+
+    #[test]
+    fn test_check_zk_msg_golden() {
+        let amount = check_zk_msg(&inputs(GOLDEN_USDC), CW20, RECIPIENT).unwrap();
+
+        assert_eq!(amount, 7_191_271);
+    }
+
+    #[test]
+    fn test_check_zk_msg_foreign_recipient() {
+        let other = "neutron1foreignrecipient";
+
+        assert!(check_zk_msg(&inputs(GOLDEN_USDC), CW20, other).is_err());
+    }
+
+    #[test]
+    fn test_check_zk_msg_foreign_cw20() {
+        let other = "neutron1foreigncw20";
+
+        assert!(check_zk_msg(&inputs(GOLDEN_USDC), other, RECIPIENT).is_err());
+    }
+
+    #[test]
+    fn test_check_zk_msg_malformed() {
+        // no circuit output after the coprocessor root
+        assert!(check_zk_msg(&inputs(&[]), CW20, RECIPIENT).is_err());
+
+        // an output the contract would not parse as a zk message
+        let mut output: serde_json::Value = serde_json::from_slice(GOLDEN_USDC).unwrap();
+        output["header"] = serde_json::json!(1);
+        let output = serde_json::to_vec(&output).unwrap();
+        assert!(check_zk_msg(&inputs(&output), CW20, RECIPIENT).is_err());
+    }
+}
@@ -1,17 +1,25 @@
 pub mod coordinator;
 pub mod strategy;
 
-use std::fs;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
 
 use common::NeutronStrategyConfig;
 use common::OUTPUTS_DIR;
 use dotenv::dotenv;
 use log::{info, warn};
+use storage_proof_core::params::CircuitParams;
 use strategy::Strategy;
 use valence_coordinator_sdk::coordinator::ValenceCoordinator;
 
 const RUNNER: &str = "runner";
 
+/// symbol of the manifest token to run the strategy of, unset for a
+/// single token deployment
+const TOKEN_ENV: &str = "STRATEGY_TOKEN";
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // load environment variables
@@ -22,13 +30,54 @@ async fn main() -> anyhow::Result<()> {
 
     info!(target: RUNNER, "starting the strategist runner");
 
-    let neutron_cfg_path = format!("{OUTPUTS_DIR}/neutron_strategy_config.toml");
+    // the artifacts of a token of a manifest deployment live in a
+    // directory named after it
+    let artifacts_dir = match env::var(TOKEN_ENV) {
+        Ok(token) => Path::new(OUTPUTS_DIR).join(token.to_lowercase()),
+        Err(_) => PathBuf::from(OUTPUTS_DIR),
+    };
+    let neutron_cfg_path = artifacts_dir.join("neutron_strategy_config.toml");
 
-    info!(target: RUNNER, "Using ntrn config: {neutron_cfg_path}");
+    info!(target: RUNNER, "Using ntrn config: {neutron_cfg_path:?}");
 
-    let parameters = fs::read_to_string(neutron_cfg_path)?;
+    let parameters = fs::read_to_string(&neutron_cfg_path)?;
 
     let neutron_cfg: NeutronStrategyConfig = toml::from_str(&parameters)?;
+    anyhow::ensure!(
+        !neutron_cfg.erc20.is_empty() && !neutron_cfg.holder.is_empty(),
+        "{neutron_cfg_path:?} sets no erc20 or holder, set the erc20 holder in the deploy inputs"
+    );
+
+    // the params the deploy script built the circuit with; a mismatch
+    // means the artifacts are out of date
+    let params_path = artifacts_dir.join("circuit_params.toml");
+    let params = fs::read_to_string(&params_path)
+        .map_err(|e| anyhow::anyhow!("failed to read the circuit params {params_path:?}: {e}"))?;
+    let params: CircuitParams = toml::from_str(&params)
+        .map_err(|e| anyhow::anyhow!("invalid circuit params {params_path:?}: {e}"))?;
+    let params_hash = hex::encode(params.hash());
+    let deployed_params_hash = neutron_cfg.circuit_params_hash.clone().ok_or_else(|| {
+        anyhow::anyhow!(
+            "{neutron_cfg_path:?} does not record the circuit params hash, the deployed \
+             circuit params are unknown: redeploy the coprocessor app"
+        )
+    })?;
+    anyhow::ensure!(
+        deployed_params_hash == params_hash,
+        "deployed circuit params hash {deployed_params_hash} does not match the hash \
+         {params_hash} of {params_path:?}"
+    );
+    anyhow::ensure!(
+        params.cw20_addr == neutron_cfg.cw20
+            && params
+                .erc20_allowlist
+                .iter()
+                .any(|erc20| erc20.eq_ignore_ascii_case(&neutron_cfg.erc20)),
+        "circuit params {params_path:?} do not mint {} proofs on {}",
+        neutron_cfg.erc20,
+        neutron_cfg.cw20
+    );
+    info!(target: RUNNER, "circuit params hash: {params_hash}");
 
     let strategy = Strategy::new(neutron_cfg).await?;
 
@@ -1,6 +1,6 @@
 use std::env;
 
-use common::NeutronStrategyConfig;
+use common::{NeutronStrategyConfig, signer::SignerSource};
 use valence_domain_clients::clients::{coprocessor::CoprocessorClient, neutron::NeutronClient};
 
 pub struct Strategy {
@@ -25,7 +25,7 @@ impl Strategy {
     pub async fn new(cfg: NeutronStrategyConfig) -> anyhow::Result<Self> {
         dotenv::dotenv().ok();
 
-        let mnemonic = env::var("MNEMONIC")?;
+        let mnemonic = SignerSource::from_env().mnemonic()?;
         let label = env::var("LABEL")?;
         let strategy_timeout: u64 = env::var("STRATEGY_TIMEOUT")?.parse()?;
 